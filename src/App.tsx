@@ -239,9 +239,11 @@ const App: React.FC = () => {
         // 转换Blob结果为文件路径格式
         result = {
           success: true,
+          cancelled: false,
           output_files: blobs.map((_, index) => `segment_${index + 1}.mp4`),
           errors: [],
-          processing_time: 0
+          processing_time: 0,
+          segments: []
         };
         
         // 下载生成的文件
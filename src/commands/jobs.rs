@@ -0,0 +1,48 @@
+use tauri::{State, WebviewWindow};
+use crate::commands::video::SplitRequestFrontend;
+use crate::models::*;
+use crate::AppState;
+
+#[tauri::command]
+pub async fn enqueue_split(
+    request: SplitRequestFrontend,
+    priority: Option<i32>,
+    window: WebviewWindow,
+    state: State<'_, AppState>,
+) -> Result<JobId, String> {
+    let split_request = request.into_split_request()?;
+    Ok(state.job_manager.enqueue(split_request, priority.unwrap_or(0), &window))
+}
+
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<JobInfo>, String> {
+    Ok(state.job_manager.list())
+}
+
+#[tauri::command]
+pub async fn get_job(job_id: JobId, state: State<'_, AppState>) -> Result<JobInfo, String> {
+    state.job_manager.get(job_id)
+        .ok_or_else(|| format!("Job not found: {}", job_id))
+}
+
+// 运行中的任务会先被取消再删除
+#[tauri::command]
+pub async fn remove_job(job_id: JobId, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.job_manager.remove(job_id))
+}
+
+// 继续上次中断的任务，已完成且完好的段不会重新处理
+#[tauri::command]
+pub async fn resume_job(
+    job_id: JobId,
+    window: WebviewWindow,
+    state: State<'_, AppState>,
+) -> Result<JobInfo, String> {
+    state.job_manager.resume(job_id, &window)
+}
+
+#[tauri::command]
+pub async fn set_max_concurrent_jobs(limit: usize, state: State<'_, AppState>) -> Result<(), String> {
+    state.job_manager.set_max_concurrent(limit);
+    Ok(())
+}
@@ -1,3 +1,4 @@
 pub mod video;
 pub mod file;
-pub mod system;
\ No newline at end of file
+pub mod system;
+pub mod jobs;
\ No newline at end of file
@@ -30,7 +30,7 @@ pub async fn get_system_info() -> Result<SystemInfo, String> {
     let memory_total = sys.total_memory();
     let disk_total = sys.total_memory(); // Using memory as proxy for now
     let disk_usage = sys.used_memory();
-    let cpu_cores = sys.cpus().len();
+    let cpu_cores = crate::utils::cpu_core_count();
     
     Ok(SystemInfo {
         os,
@@ -6,9 +6,11 @@ use crate::AppState;
 #[derive(Serialize, Deserialize)]
 pub struct SplitResult {
     pub success: bool,
+    pub cancelled: bool,
     pub output_files: Vec<String>,
     pub errors: Vec<String>,
     pub processing_time: f64,
+    pub segments: Vec<SegmentResult>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,6 +23,8 @@ pub struct DetectScenesRequest {
     pub video_path: String,
     pub threshold: f32,
     pub min_duration: Option<f64>,
+    #[serde(default)]
+    pub detector: SceneDetector,
 }
 
 #[tauri::command]
@@ -28,18 +32,165 @@ pub async fn get_video_info(
     path: String,
     state: State<'_, AppState>,
 ) -> Result<VideoInfo, String> {
-    let video_processor = state.video_processor.lock().await;
-    video_processor.get_video_info(&path)
+    state.video_processor.get_video_info(&path)
         .await
         .map_err(|e| e.to_string())
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct SceneCurveRequest {
+    pub video_path: String,
+    #[serde(default)]
+    pub detector: SceneDetector,
+    pub max_points: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RethresholdScenesRequest {
+    pub video_path: String,
+    #[serde(default)]
+    pub detector: SceneDetector,
+    pub threshold: f32,
+    pub min_duration_ms: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SplitRequestFrontend {
     pub video_path: String,
     pub output_dir: String,
     pub output_format: String,
     pub split_type: serde_json::Value, // 使用通用的JSON值来处理复杂的嵌套结构
+    #[serde(default)]
+    pub split_mode: SplitMode,
+    #[serde(default)]
+    pub workers: Option<usize>,
+    #[serde(default)]
+    pub failure_policy: FailurePolicy,
+    #[serde(default)]
+    pub verify: Option<VerifyOptions>,
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    #[serde(default)]
+    pub constraints: SegmentConstraints,
+}
+
+impl SplitRequestFrontend {
+    // 转换前端请求到后端格式
+    pub fn into_split_request(self) -> Result<SplitRequest, String> {
+        let split_type = if let Some(time_obj) = self.split_type.get("time") {
+            let duration = time_obj.get("duration").and_then(|v| v.as_f64()).unwrap_or(60.0);
+            let count = time_obj.get("count").and_then(|v| v.as_u64()).map(|v| v as u32);
+            println!("Time split: duration={}, count={:?}", duration, count);
+            crate::models::SplitType::Time { duration, count }
+        } else if let Some(scenes_obj) = self.split_type.get("scenes") {
+            let threshold = scenes_obj.get("threshold").and_then(|v| v.as_f64()).unwrap_or(0.3) as f32;
+            let min_duration = scenes_obj.get("min_duration").and_then(|v| v.as_f64());
+            let group = scenes_obj.get("group")
+                .filter(|v| !v.is_null())
+                .cloned()
+                .map(serde_json::from_value::<crate::models::SceneGrouping>)
+                .transpose()
+                .map_err(|e| format!("Invalid scene grouping: {}", e))?;
+            let detector = scenes_obj.get("detector")
+                .filter(|v| !v.is_null())
+                .cloned()
+                .map(serde_json::from_value::<crate::models::SceneDetector>)
+                .transpose()
+                .map_err(|e| format!("Invalid scene detector: {}", e))?
+                .unwrap_or_default();
+            println!(
+                "Scenes split: threshold={}, min_duration={:?}, detector={:?}, group={:?}",
+                threshold, min_duration, detector, group
+            );
+            crate::models::SplitType::Scenes { threshold, min_duration, detector, group }
+        } else if let Some(manual_obj) = self.split_type.get("manual") {
+            let split_points = manual_obj.get("split_points")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_f64()).collect())
+                .unwrap_or_default();
+            let titles = manual_obj.get("titles")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().map(|v| v.as_str().unwrap_or_default().to_string()).collect())
+                .unwrap_or_default();
+            println!("Manual split: split_points={:?}, titles={:?}", split_points, titles);
+            crate::models::SplitType::Manual { split_points, titles }
+        } else if let Some(size_obj) = self.split_type.get("size") {
+            let max_bytes = size_obj.get("max_bytes")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| "Size split requires max_bytes".to_string())?;
+            println!("Size split: max_bytes={}", max_bytes);
+            crate::models::SplitType::Size { max_bytes }
+        } else if let Some(chapters_obj) = self.split_type.get("chapters") {
+            let indices = chapters_obj.get("indices")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_u64()).map(|v| v as usize).collect());
+            println!("Chapters split: indices={:?}", indices);
+            crate::models::SplitType::Chapters { indices }
+        } else if let Some(silence_obj) = self.split_type.get("silence") {
+            let noise_db = silence_obj.get("noise_db").and_then(|v| v.as_f64()).unwrap_or(-30.0);
+            let min_silence = silence_obj.get("min_silence").and_then(|v| v.as_f64()).unwrap_or(0.5);
+            let min_length = silence_obj.get("min_length").and_then(|v| v.as_f64()).unwrap_or(30.0);
+            let max_length = silence_obj.get("max_length").and_then(|v| v.as_f64()).unwrap_or(300.0);
+            println!(
+                "Silence split: noise_db={}, min_silence={}, length={}..{}",
+                noise_db, min_silence, min_length, max_length
+            );
+            crate::models::SplitType::Silence { noise_db, min_silence, min_length, max_length }
+        } else if let Some(black_obj) = self.split_type.get("black_frames") {
+            let pixel_threshold = black_obj.get("pixel_threshold").and_then(|v| v.as_f64()).unwrap_or(0.1);
+            let picture_threshold = black_obj.get("picture_threshold").and_then(|v| v.as_f64()).unwrap_or(0.98);
+            let min_duration = black_obj.get("min_duration").and_then(|v| v.as_f64()).unwrap_or(0.5);
+            let drop_black = black_obj.get("drop_black").and_then(|v| v.as_bool()).unwrap_or(false);
+            println!(
+                "Black frames split: pix_th={}, pic_th={}, min_duration={}, drop_black={}",
+                pixel_threshold, picture_threshold, min_duration, drop_black
+            );
+            crate::models::SplitType::BlackFrames { pixel_threshold, picture_threshold, min_duration, drop_black }
+        } else if let Some(subtitles_obj) = self.split_type.get("subtitles") {
+            let source = subtitles_obj.get("source")
+                .cloned()
+                .ok_or_else(|| "Subtitles split requires a source".to_string())
+                .and_then(|v| serde_json::from_value::<crate::models::SubtitleSource>(v)
+                    .map_err(|e| format!("Invalid subtitle source: {}", e)))?;
+            let target_duration = subtitles_obj.get("target_duration").and_then(|v| v.as_f64()).unwrap_or(0.0);
+            println!("Subtitles split: source={:?}, target_duration={}", source, target_duration);
+            crate::models::SplitType::Subtitles { source, target_duration }
+        } else if let Some(ranges_obj) = self.split_type.get("ranges") {
+            let ranges = ranges_obj.get("ranges")
+                .cloned()
+                .map(serde_json::from_value::<Vec<crate::models::RangeSelection>>)
+                .transpose()
+                .map_err(|e| format!("Invalid ranges: {}", e))?
+                .unwrap_or_default();
+            let join = ranges_obj.get("join").and_then(|v| v.as_bool()).unwrap_or(false);
+            println!("Ranges split: {} ranges, join={}", ranges.len(), join);
+            crate::models::SplitType::Ranges { ranges, join }
+        } else if let Some(jingle_obj) = self.split_type.get("jingle") {
+            let reference_path = jingle_obj.get("reference_path")
+                .and_then(|v| v.as_str())
+                .map(std::path::PathBuf::from)
+                .ok_or_else(|| "Jingle split requires reference_path".to_string())?;
+            let min_score = jingle_obj.get("min_score").and_then(|v| v.as_f64()).unwrap_or(0.7) as f32;
+            println!("Jingle split: reference={:?}, min_score={}", reference_path, min_score);
+            crate::models::SplitType::Jingle { reference_path, min_score }
+        } else {
+            println!("Invalid split_type format: {:?}", self.split_type);
+            return Err(format!("Invalid split_type format: {:?}", self.split_type));
+        };
+        
+        Ok(crate::models::SplitRequest {
+            video_path: std::path::PathBuf::from(&self.video_path),
+            split_type,
+            output_dir: std::path::PathBuf::from(&self.output_dir),
+            output_format: self.output_format,
+            split_mode: self.split_mode,
+            workers: self.workers,
+            failure_policy: self.failure_policy,
+            verify: self.verify,
+            overwrite_policy: self.overwrite_policy,
+            constraints: self.constraints,
+        })
+    }
 }
 
 #[tauri::command]
@@ -50,45 +201,27 @@ pub async fn split_video(
 ) -> Result<SplitResult, String> {
     println!("Received split request: {:?}", request);
     
-    let video_splitter = state.video_splitter.lock().await;
+    let split_request = request.into_split_request()?;
     
-    // 转换前端请求到后端格式
-    let split_type = if let Some(time_obj) = request.split_type.get("time") {
-        let duration = time_obj.get("duration").and_then(|v| v.as_f64()).unwrap_or(60.0);
-        let count = time_obj.get("count").and_then(|v| v.as_u64()).map(|v| v as u32);
-        println!("Time split: duration={}, count={:?}", duration, count);
-        crate::models::SplitType::Time { duration, count }
-    } else if let Some(scenes_obj) = request.split_type.get("scenes") {
-        let threshold = scenes_obj.get("threshold").and_then(|v| v.as_f64()).unwrap_or(0.3) as f32;
-        let min_duration = scenes_obj.get("min_duration").and_then(|v| v.as_f64());
-        println!("Scenes split: threshold={}, min_duration={:?}", threshold, min_duration);
-        crate::models::SplitType::Scenes { threshold, min_duration }
-    } else if let Some(manual_obj) = request.split_type.get("manual") {
-        let split_points = manual_obj.get("split_points")
-            .and_then(|v| v.as_array())
-            .map(|arr| arr.iter().filter_map(|v| v.as_f64()).collect())
-            .unwrap_or_default();
-        println!("Manual split: split_points={:?}", split_points);
-        crate::models::SplitType::Manual { split_points }
-    } else {
-        println!("Invalid split_type format: {:?}", request.split_type);
-        return Err(format!("Invalid split_type format: {:?}", request.split_type));
-    };
+    println!("Queueing split job with request: {:?}", split_request);
     
-    let split_request = crate::models::SplitRequest {
-        video_path: std::path::PathBuf::from(&request.video_path),
-        split_type,
-        output_dir: std::path::PathBuf::from(&request.output_dir),
-        output_format: request.output_format,
-    };
+    // 通过任务队列运行，其他命令不会被阻塞，也可以随时取消
+    let job_id = state.job_manager.enqueue(split_request, 0, &window);
+    let job = state.job_manager.wait(job_id).await
+        .ok_or_else(|| format!("Job {} was removed", job_id))?;
     
-    println!("Calling video_splitter.split_video with request: {:?}", split_request);
+    let result = match (job.result, job.error) {
+        (Some(result), _) => result,
+        (None, error) => {
+            let error = error.unwrap_or_else(|| "Job cancelled".to_string());
+            println!("Split video error: {}", error);
+            return Err(error);
+        }
+    };
     
-    let result = video_splitter.split_video(split_request, &window).await
-        .map_err(|e| {
-            println!("Split video error: {}", e);
-            e.to_string()
-        })?;
+    if result.cancelled {
+        println!("Split cancelled after {} files", result.output_files.len());
+    }
     
     // 转换结果中的PathBuf为字符串
     let output_files: Vec<String> = result.output_files
@@ -100,20 +233,83 @@ pub async fn split_video(
     
     Ok(SplitResult {
         success: result.success,
+        cancelled: result.cancelled,
         output_files,
         errors: result.errors,
         processing_time: result.processing_time,
+        segments: result.segments,
     })
 }
 
+// 不指定任务ID时取消所有正在运行的任务
+#[tauri::command]
+pub async fn cancel_split(
+    job_id: Option<JobId>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    match job_id {
+        Some(job_id) => Ok(state.job_manager.cancel(job_id)),
+        None => Ok(state.job_manager.cancel_running() > 0),
+    }
+}
+
 #[tauri::command]
 pub async fn detect_scenes(
     request: DetectScenesRequest,
+    window: WebviewWindow,
     state: State<'_, AppState>,
 ) -> Result<Vec<ScenePoint>, String> {
-    let video_processor = state.video_processor.lock().await;
     let min_duration = request.min_duration.unwrap_or(2.0);
-    video_processor.detect_scenes(&request.video_path, request.threshold, min_duration)
+    state.video_processor.detect_scenes(&request.video_path, &request.detector, request.threshold, min_duration, Some(&window), None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_scene_curve(
+    request: SceneCurveRequest,
+    window: WebviewWindow,
+    state: State<'_, AppState>,
+) -> Result<SceneCurve, String> {
+    let max_points = request.max_points.unwrap_or(2000);
+    state.video_processor.scene_curve(&request.video_path, &request.detector, max_points, Some(&window), None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 使用 get_scene_curve 或 detect_scenes 保存的分数，调整阈值时不需要重新分析
+#[tauri::command]
+pub async fn rethreshold_scenes(
+    request: RethresholdScenesRequest,
+    state: State<'_, AppState>,
+) -> Result<Vec<ScenePoint>, String> {
+    let min_duration = request.min_duration_ms as f64 / 1000.0;
+    state.video_processor.rethreshold_scenes(&request.video_path, &request.detector, request.threshold, min_duration)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DetectJinglesRequest {
+    pub video_path: String,
+    pub reference_path: String,
+    pub min_score: Option<f32>,
+}
+
+#[tauri::command]
+pub async fn detect_jingles(
+    request: DetectJinglesRequest,
+    window: WebviewWindow,
+    state: State<'_, AppState>,
+) -> Result<Vec<AudioMatch>, String> {
+    let min_score = request.min_score.unwrap_or(0.7);
+    state.video_processor.detect_jingles(
+        &request.video_path,
+        std::path::Path::new(&request.reference_path),
+        min_score,
+        Some(&window),
+        None,
+    )
         .await
         .map_err(|e| e.to_string())
 }
@@ -125,8 +321,17 @@ pub async fn get_video_thumbnail(
     output_path: String,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    let video_processor = state.video_processor.lock().await;
-    video_processor.extract_thumbnail(&video_path, time_seconds, &output_path)
+    state.video_processor.extract_thumbnail(&video_path, time_seconds, &output_path)
         .await
         .map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+// 解析 "00:00 Intro / 03:12 Setup" 这类时间戳列表，返回带标题的手动分割
+#[tauri::command]
+pub async fn parse_timestamps(text: String) -> Result<SplitType, String> {
+    let entries = crate::utils::parse_timestamp_list(&text).map_err(|e| e.to_string())?;
+    println!("Parsed {} timestamps", entries.len());
+    
+    let (split_points, titles) = entries.into_iter().unzip();
+    Ok(SplitType::Manual { split_points, titles })
+}
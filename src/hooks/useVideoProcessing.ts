@@ -1,4 +1,5 @@
 import { useState, useCallback } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { VideoService } from '../services';
 import { VideoInfo, SplitRequest, SplitResult, SplitProgress, ScenePoint } from '../types';
 
@@ -29,8 +30,20 @@ export const useVideoProcessing = () => {
     setProgress(null);
     setResults(null);
 
+    // 监听后端发送的分割进度事件
+    const unlisten = await listen<SplitProgress>('split-progress', (event) => {
+      setProgress(event.payload);
+    });
+    // 静音检测等分析阶段的进度
+    const unlistenAnalysis = await listen<SplitProgress>('analysis-progress', (event) => {
+      setProgress(event.payload);
+    });
+    // 按场景分割时，规划阶段的场景检测进度
+    const unlistenScenes = await listen<SplitProgress>('scene-detection-progress', (event) => {
+      setProgress(event.payload);
+    });
+
     try {
-      // 模拟进度更新（实际应该通过Tauri事件监听）
       setProgress({
         current: 0,
         total: 100,
@@ -38,7 +51,6 @@ export const useVideoProcessing = () => {
         percentage: 0,
       });
 
-      // 这里应该监听Tauri的进度事件
       const result = await VideoService.splitVideo(request);
       setResults(result);
       
@@ -51,6 +63,9 @@ export const useVideoProcessing = () => {
     } catch (err) {
       setError(err instanceof Error ? err.message : '视频分割失败');
     } finally {
+      unlisten();
+      unlistenAnalysis();
+      unlistenScenes();
       setLoading(false);
       setTimeout(() => setProgress(null), 2000);
     }
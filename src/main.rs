@@ -3,7 +3,6 @@
 
 use tauri::Manager;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 mod commands;
 mod services;
@@ -12,10 +11,14 @@ mod models;
 
 use services::video_processor::VideoProcessor;
 use services::splitter::VideoSplitter;
+use services::job_manager::JobManager;
+
+// 默认同时运行的分割任务数，CPU核数在运行中的任务之间平分，每个任务内部再并行处理多个分段
+const DEFAULT_MAX_CONCURRENT_JOBS: usize = 2;
 
 pub struct AppState {
-    pub video_processor: Arc<Mutex<VideoProcessor>>,
-    pub video_splitter: Arc<Mutex<VideoSplitter>>,
+    pub video_processor: Arc<VideoProcessor>,
+    pub job_manager: Arc<JobManager>,
 }
 
 fn main() {
@@ -29,13 +32,24 @@ fn main() {
             let window = app.get_webview_window("main").unwrap();
             
             // Initialize services
-            let video_processor = Arc::new(Mutex::new(VideoProcessor::new()));
-            let video_splitter = Arc::new(Mutex::new(VideoSplitter::new()));
+            // 场景分析结果缓存在应用缓存目录中，可以随时删除
+            let cache_dir = app.path().app_cache_dir()?;
+            let video_processor = Arc::new(VideoProcessor::new(cache_dir.join("scene-scores")));
+            let video_splitter = Arc::new(VideoSplitter::new(video_processor.clone()));
+            
+            // 未完成的任务保存在应用数据目录中，重启后可以继续
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            let job_manager = Arc::new(JobManager::new(
+                video_splitter,
+                DEFAULT_MAX_CONCURRENT_JOBS,
+                data_dir.join("jobs.json"),
+            ));
             
             // Manage state
             app.manage(AppState {
                 video_processor,
-                video_splitter,
+                job_manager,
             });
             
             // Set window size and title
@@ -47,8 +61,19 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             commands::video::get_video_info,
             commands::video::split_video,
+            commands::video::cancel_split,
             commands::video::detect_scenes,
+            commands::video::get_scene_curve,
+            commands::video::rethreshold_scenes,
+            commands::video::detect_jingles,
             commands::video::get_video_thumbnail,
+            commands::video::parse_timestamps,
+            commands::jobs::enqueue_split,
+            commands::jobs::list_jobs,
+            commands::jobs::get_job,
+            commands::jobs::remove_job,
+            commands::jobs::resume_job,
+            commands::jobs::set_max_concurrent_jobs,
             commands::file::select_video_file,
             commands::file::select_output_directory,
             commands::file::file_exists,
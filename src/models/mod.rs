@@ -12,6 +12,18 @@ pub struct VideoInfo {
     pub bitrate: u64,
     pub format: String,
     pub size: u64,
+    #[serde(default)]
+    pub chapters: Vec<Chapter>,
+}
+
+// 容器中记录的章节
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Chapter {
+    // 章节在文件中的顺序，从0开始
+    pub index: usize,
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,24 +32,288 @@ pub struct SplitRequest {
     pub split_type: SplitType,
     pub output_dir: PathBuf,
     pub output_format: String,
+    #[serde(default)]
+    pub split_mode: SplitMode,
+    // 并行处理的分段数，默认为CPU核心数
+    #[serde(default)]
+    pub workers: Option<usize>,
+    #[serde(default)]
+    pub failure_policy: FailurePolicy,
+    // 为空时不校验输出
+    #[serde(default)]
+    pub verify: Option<VerifyOptions>,
+    #[serde(default)]
+    pub overwrite_policy: OverwritePolicy,
+    #[serde(default)]
+    pub constraints: SegmentConstraints,
+}
+
+// 对所有分割方式生效的分段长度限制（秒）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SegmentConstraints {
+    // 短于该长度的段并入相邻的段
+    #[serde(default)]
+    pub min_length: Option<f64>,
+    // 长于该长度的段平均拆分
+    #[serde(default)]
+    pub max_length: Option<f64>,
+    // 每段向前、向后多包含的时长，相邻两段因此重叠 pre_roll + post_roll
+    #[serde(default)]
+    pub pre_roll: f64,
+    #[serde(default)]
+    pub post_roll: f64,
+}
+
+// 输出文件已存在时的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum OverwritePolicy {
+    #[default]
+    #[serde(rename = "overwrite")]
+    Overwrite,
+    // 保留已有文件，该段视为已完成
+    #[serde(rename = "skip")]
+    Skip,
+    // 在文件名后加数字，如 segment_video_001_1.mp4
+    #[serde(rename = "suffix")]
+    Suffix,
+    #[serde(rename = "fail")]
+    Fail,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifyOptions {
+    // 输出时长与计划时长允许的误差（秒）
+    #[serde(default = "default_duration_tolerance")]
+    pub duration_tolerance: f64,
+    // 完整解码一遍检查是否有错误，耗时较长
+    #[serde(default)]
+    pub decode_check: bool,
+}
+
+fn default_duration_tolerance() -> f64 {
+    0.5
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum SplitMode {
+    // 直接复制流，分割点对齐到关键帧
+    #[serde(rename = "copy")]
+    Copy,
+    // 重新编码，分割点精确到帧
+    #[default]
+    #[serde(rename = "reencode")]
+    Reencode,
+    // 只重新编码分割点所在的GOP，中间部分直接复制
+    #[serde(rename = "smart")]
+    Smart,
+}
+
+// 某个分段失败时的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum FailurePolicy {
+    // 第一个失败后不再开始新的分段
+    #[default]
+    #[serde(rename = "stop")]
+    Stop,
+    // 失败的分段重试，重试间隔按 backoff_ms 倍增，其余分段继续处理
+    #[serde(rename = "continue")]
+    Continue { retries: u32, backoff_ms: u64 },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum SplitType {
     #[serde(rename = "time")]
     Time { duration: f64, count: Option<u32> },
+    // threshold 对 ffmpeg 和 content 检测器是分数阈值（0到1），对 adaptive 是与周围平均分之比
     #[serde(rename = "scenes")]
-    Scenes { threshold: f32, min_duration: Option<f64> },
+    Scenes {
+        threshold: f32,
+        min_duration: Option<f64>,
+        #[serde(default)]
+        detector: SceneDetector,
+        // 为空时在每个场景切换处切分
+        #[serde(default)]
+        group: Option<SceneGrouping>,
+    },
+    // titles[i] 为从 split_points[i] 开始的段的标题，空字符串表示没有标题
     #[serde(rename = "manual")]
-    Manual { split_points: Vec<f64> },
+    Manual {
+        split_points: Vec<f64>,
+        #[serde(default)]
+        titles: Vec<String>,
+    },
+    // 每段文件不超过 max_bytes 字节
+    #[serde(rename = "size")]
+    Size { max_bytes: u64 },
+    // 按章节分割，indices 为空时输出全部章节
+    #[serde(rename = "chapters")]
+    Chapters {
+        #[serde(default)]
+        indices: Option<Vec<usize>>,
+    },
+    // 在静音处切分，每段长度尽量落在 min_length 到 max_length 之间
+    #[serde(rename = "silence")]
+    Silence {
+        // 低于该音量（dB）视为静音
+        noise_db: f64,
+        // 静音至少持续的秒数
+        min_silence: f64,
+        min_length: f64,
+        max_length: f64,
+    },
+    // 在黑场处切分，drop_black 为 true 时输出中不包含黑场本身
+    #[serde(rename = "black_frames")]
+    BlackFrames {
+        // 像素亮度低于该比例视为黑色像素
+        pixel_threshold: f64,
+        // 黑色像素占比超过该值视为黑帧
+        picture_threshold: f64,
+        // 黑场至少持续的秒数
+        min_duration: f64,
+        #[serde(default)]
+        drop_black: bool,
+    },
+    // 只在字幕之间的空隙切分，连续的字幕合并到至少 target_duration 秒
+    // target_duration 为0时每条字幕一段
+    #[serde(rename = "subtitles")]
+    Subtitles {
+        source: SubtitleSource,
+        #[serde(default)]
+        target_duration: f64,
+    },
+    // 保留或去掉指定范围，join 为 true 时把保留的部分拼接成一个文件
+    // 只有 discard 范围时保留其余全部内容
+    #[serde(rename = "ranges")]
+    Ranges {
+        ranges: Vec<RangeSelection>,
+        #[serde(default)]
+        join: bool,
+    },
+    // 在参考音频（片头、转场音效等）每次出现的位置切分
+    #[serde(rename = "jingle")]
+    Jingle {
+        reference_path: PathBuf,
+        // 相关系数不低于该值才视为匹配，范围0到1
+        min_score: f32,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RangeSelection {
+    pub start: f64,
+    pub end: f64,
+    pub action: RangeAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RangeAction {
+    #[serde(rename = "keep")]
+    Keep,
+    #[serde(rename = "discard")]
+    Discard,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum SceneDetector {
+    // ffmpeg 的 scene 分数
+    #[default]
+    #[serde(rename = "ffmpeg")]
+    Ffmpeg,
+    // 本地计算的HSV直方图与边缘变化分数
+    #[serde(rename = "content")]
+    Content,
+    // 与前后 window 帧的平均分比较，min_score 为切点的最低分数
+    #[serde(rename = "adaptive")]
+    Adaptive { window: usize, min_score: f32 },
+    // 在 content 分数的基础上，按 window 帧内的亮度和差异变化识别淡入淡出与叠化
+    #[serde(rename = "transitions")]
+    Transitions { window: usize },
+}
+
+// 合并相邻场景，使每段长度在 min_length 到 max_length 秒之间
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SceneGrouping {
+    pub min_length: f64,
+    pub max_length: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum SubtitleSource {
+    // SRT、WebVTT 或 ASS 文件
+    #[serde(rename = "file")]
+    File { path: PathBuf },
+    // 视频中的第几条字幕流，从0开始
+    #[serde(rename = "stream")]
+    Stream { index: usize },
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SplitResult {
     pub success: bool,
+    // 任务被用户取消
+    pub cancelled: bool,
     pub output_files: Vec<PathBuf>,
     pub errors: Vec<String>,
     pub processing_time: f64,
+    pub segments: Vec<SegmentResult>,
+}
+
+// 分割前计算好的单个分割段
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SegmentPlan {
+    pub index: usize,
+    pub requested_start: f64,
+    pub requested_end: f64,
+    pub start: f64,
+    pub end: f64,
+    // 用于文件名和输出元数据
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SegmentResult {
+    pub index: usize,
+    // 请求的分割范围
+    pub requested_start: f64,
+    pub requested_end: f64,
+    // 实际使用的分割范围（复制模式下对齐到关键帧）
+    pub start: f64,
+    pub end: f64,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub output_file: PathBuf,
+    pub status: SegmentStatus,
+    // 实际运行ffmpeg的次数
+    pub attempts: u32,
+    // 最后一次失败时ffmpeg输出的末尾几行
+    pub error: Option<String>,
+    pub verification: Option<VerificationReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificationReport {
+    pub passed: bool,
+    pub expected_duration: f64,
+    pub duration: Option<f64>,
+    // 输出文件中的流类型，如 video、audio
+    pub streams: Vec<String>,
+    pub missing_streams: Vec<String>,
+    pub decode_errors: Option<String>,
+    pub issues: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SegmentStatus {
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+    // 因前面的分段失败而未处理
+    #[serde(rename = "skipped")]
+    Skipped,
+    #[serde(rename = "cancelled")]
+    Cancelled,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -46,11 +322,122 @@ pub struct ProgressInfo {
     pub total: u32,
     pub message: String,
     pub percentage: f64,
+    // 当前段的进度
+    pub segment_percentage: f64,
+    // 编码速度（实时倍数）
+    pub speed: Option<f64>,
+    // 预计剩余时间（秒）
+    pub eta: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+// 参考音频在视频中的一次出现
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioMatch {
+    pub time: f64,
+    pub duration: f64,
+    pub confidence: f32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ScenePoint {
     pub time: f64,
+    // 视频流时间基下的PTS，用于精确定位切点
+    pub pts: i64,
+    // 该帧的场景变化分数，0到1
     pub confidence: f32,
     pub frame_number: u64,
+    #[serde(default)]
+    pub transition: TransitionType,
+}
+
+// 场景之间的转场方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum TransitionType {
+    #[default]
+    #[serde(rename = "cut")]
+    Cut,
+    // 画面逐渐变黑，time 为第一帧黑场
+    #[serde(rename = "fade_out")]
+    FadeOut,
+    // 从黑场逐渐显现，time 为黑场后的第一帧
+    #[serde(rename = "fade_in")]
+    FadeIn,
+    // 两个镜头叠化，time 为叠化的中点
+    #[serde(rename = "dissolve")]
+    Dissolve,
+}
+
+// 场景分析得到的单帧分数，阈值判断在此基础上单独进行
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FrameScore {
+    pub frame_number: u64,
+    pub pts: i64,
+    pub time: f64,
+    pub score: f32,
+    // 该帧的平均亮度，0到1
+    #[serde(default)]
+    pub luma: f32,
+}
+
+// 用于显示的场景分数曲线，points 是降采样后保留的帧
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SceneCurve {
+    // 降采样前的帧数
+    pub frame_count: usize,
+    pub duration: f64,
+    pub points: Vec<FrameScore>,
+}
+
+pub type JobId = u64;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    // 应用退出时未完成，等待用户决定是否继续
+    #[serde(rename = "interrupted")]
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub status: JobStatus,
+    // 数值越大越先运行
+    pub priority: i32,
+    pub request: SplitRequest,
+    // 规划好的分割段，为空表示尚未规划
+    #[serde(default)]
+    pub plan: Vec<SegmentPlan>,
+    #[serde(default)]
+    pub completed_segments: Vec<SegmentResult>,
+    pub result: Option<SplitResult>,
+    pub error: Option<String>,
+    // Unix时间戳（秒）
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
 }
\ No newline at end of file
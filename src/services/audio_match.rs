@@ -0,0 +1,169 @@
+use rayon::prelude::*;
+
+// 匹配时使用的单声道采样率
+pub const SAMPLE_RATE: u32 = 8000;
+
+const WINDOW: usize = 512;
+const HOP: usize = 256;
+const BANDS: usize = 16;
+const MIN_FREQUENCY: f64 = 100.0;
+const MAX_FREQUENCY: f64 = 4000.0;
+
+// 每帧各频带的对数能量
+pub type Features = Vec<[f32; BANDS]>;
+
+pub fn frame_duration() -> f64 {
+    HOP as f64 / SAMPLE_RATE as f64
+}
+
+// 把PCM采样转换为频带能量序列，比直接对采样做互相关快得多，也不受相位影响
+pub fn spectral_features(samples: &[f32]) -> Features {
+    if samples.len() < WINDOW {
+        return Vec::new();
+    }
+
+    let hann: Vec<f32> = (0..WINDOW)
+        .map(|i| {
+            let x = std::f32::consts::PI * 2.0 * i as f32 / (WINDOW - 1) as f32;
+            0.5 - 0.5 * x.cos()
+        })
+        .collect();
+
+    // 频带边界按对数间隔分布，对应FFT的频点下标
+    let bin_of = |f: f64| (f / SAMPLE_RATE as f64 * WINDOW as f64).round() as usize;
+    let edges: Vec<usize> = (0..=BANDS)
+        .map(|i| {
+            let ratio = MAX_FREQUENCY / MIN_FREQUENCY;
+            bin_of(MIN_FREQUENCY * ratio.powf(i as f64 / BANDS as f64))
+        })
+        .collect();
+
+    let frames = (samples.len() - WINDOW) / HOP + 1;
+    (0..frames)
+        .into_par_iter()
+        .map(|frame| {
+            let start = frame * HOP;
+            let mut re: Vec<f32> = samples[start..start + WINDOW]
+                .iter()
+                .zip(&hann)
+                .map(|(s, w)| s * w)
+                .collect();
+            let mut im = vec![0.0; WINDOW];
+            fft(&mut re, &mut im);
+
+            let mut bands = [0.0; BANDS];
+            for (band, value) in bands.iter_mut().enumerate() {
+                let (low, high) = (edges[band], edges[band + 1].max(edges[band] + 1));
+                let energy: f32 = (low..high).map(|k| re[k] * re[k] + im[k] * im[k]).sum();
+                *value = (energy + 1e-9).ln();
+            }
+            bands
+        })
+        .collect()
+}
+
+// 在 source 中查找与 reference 相关系数不低于 min_score 的位置
+// 返回 (帧下标, 相关系数)，相邻的匹配只保留得分最高的一个
+pub fn find_matches(source: &Features, reference: &Features, min_score: f32) -> Vec<(usize, f32)> {
+    let length = reference.len();
+    if length == 0 || source.len() < length {
+        return Vec::new();
+    }
+
+    // 参考音频去均值并归一化，之后每个位置只需要一次点积
+    let values = (length * BANDS) as f64;
+    let reference: Vec<f32> = reference.iter().flatten().copied().collect();
+    let mean = reference.iter().map(|&v| v as f64).sum::<f64>() / values;
+    let norm = reference
+        .iter()
+        .map(|&v| (v as f64 - mean).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    if norm == 0.0 {
+        return Vec::new();
+    }
+    let reference: Vec<f32> = reference
+        .iter()
+        .map(|&v| ((v as f64 - mean) / norm) as f32)
+        .collect();
+
+    // 每帧的和与平方和的前缀和，用于快速计算窗口的均值和方差
+    let mut sums = vec![0.0f64; source.len() + 1];
+    let mut squares = vec![0.0f64; source.len() + 1];
+    for (i, frame) in source.iter().enumerate() {
+        sums[i + 1] = sums[i] + frame.iter().map(|&v| v as f64).sum::<f64>();
+        squares[i + 1] = squares[i] + frame.iter().map(|&v| (v as f64).powi(2)).sum::<f64>();
+    }
+
+    let scores: Vec<f32> = (0..=source.len() - length)
+        .into_par_iter()
+        .map(|offset| {
+            let sum = sums[offset + length] - sums[offset];
+            let square = squares[offset + length] - squares[offset];
+            let deviation = (square - sum * sum / values).max(0.0).sqrt();
+            if deviation == 0.0 {
+                return 0.0;
+            }
+            let dot: f64 = source[offset..offset + length]
+                .iter()
+                .flatten()
+                .zip(&reference)
+                .map(|(&s, &r)| s as f64 * r as f64)
+                .sum();
+            (dot / deviation) as f32
+        })
+        .collect();
+
+    // 按得分从高到低选取，与已选位置距离不足参考长度的丢弃
+    let mut candidates: Vec<(usize, f32)> = scores
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, score)| score >= min_score)
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut matches: Vec<(usize, f32)> = Vec::new();
+    for (offset, score) in candidates {
+        if matches.iter().all(|&(m, _)| m.abs_diff(offset) >= length) {
+            matches.push((offset, score));
+        }
+    }
+    matches.sort_by_key(|&(offset, _)| offset);
+    matches
+}
+
+// 原地基2 FFT，长度必须是2的幂
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= n {
+        let angle = -2.0 * std::f32::consts::PI / size as f32;
+        for start in (0..n).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + size / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size <<= 1;
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+
+// 可克隆的取消标记，所有克隆共享同一个状态
+#[derive(Clone)]
+pub struct CancelToken {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    // 在取消之前一直挂起
+    pub async fn cancelled(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|&cancelled| cancelled).await;
+    }
+}
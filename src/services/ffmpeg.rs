@@ -0,0 +1,210 @@
+use crate::services::cancel::CancelToken;
+use crate::utils::{VideoError, VideoResult};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+
+// ffmpeg -progress 输出的一次进度报告
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegProgress {
+    // 已写出的时长（秒）
+    pub out_time: f64,
+    // 编码速度，相对于实时播放的倍数
+    pub speed: Option<f64>,
+}
+
+pub struct FfmpegOutput {
+    pub success: bool,
+    pub stderr: Vec<u8>,
+}
+
+// 逐行处理stderr时输出中保留的行数，用于错误信息
+const STDERR_TAIL_LINES: usize = 20;
+
+pub async fn run_with_progress<F>(
+    ffmpeg_path: &str,
+    args: &[String],
+    cancel: Option<&CancelToken>,
+    on_progress: F,
+) -> VideoResult<FfmpegOutput>
+where
+    F: FnMut(&FfmpegProgress),
+{
+    run(ffmpeg_path, args, cancel, on_progress, None::<fn(&str)>).await
+}
+
+// 每一行stderr在读到时交给 on_line 处理，输出中只保留最后几行
+// 用于每帧都输出日志的分析，避免把整个日志保存在内存中
+pub async fn run_with_stderr_lines<F, L>(
+    ffmpeg_path: &str,
+    args: &[String],
+    cancel: Option<&CancelToken>,
+    on_progress: F,
+    on_line: L,
+) -> VideoResult<FfmpegOutput>
+where
+    F: FnMut(&FfmpegProgress),
+    L: FnMut(&str),
+{
+    run(ffmpeg_path, args, cancel, on_progress, Some(on_line)).await
+}
+
+async fn run<F, L>(
+    ffmpeg_path: &str,
+    args: &[String],
+    cancel: Option<&CancelToken>,
+    mut on_progress: F,
+    on_line: Option<L>,
+) -> VideoResult<FfmpegOutput>
+where
+    F: FnMut(&FfmpegProgress),
+    L: FnMut(&str),
+{
+    let mut child = Command::new(ffmpeg_path)
+        .args(["-progress", "pipe:1", "-nostats"])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // 进度信息以 key=value 行输出到stdout，每组以 progress= 结尾
+    let read_progress = async {
+        let Some(stdout) = stdout else { return };
+        let mut lines = BufReader::new(stdout).lines();
+        let mut progress = FfmpegProgress::default();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "out_time_us" => {
+                    if let Ok(us) = value.parse::<i64>() {
+                        progress.out_time = us.max(0) as f64 / 1_000_000.0;
+                    }
+                }
+                "speed" => progress.speed = value.trim_end_matches('x').parse().ok(),
+                "progress" => on_progress(&progress),
+                _ => {}
+            }
+        }
+    };
+
+    // stderr必须同时读取，否则缓冲区写满后ffmpeg会阻塞
+    let read_stderr = async {
+        let mut buf = Vec::new();
+        let Some(mut stderr) = stderr else {
+            return buf;
+        };
+        let Some(mut on_line) = on_line else {
+            let _ = stderr.read_to_end(&mut buf).await;
+            return buf;
+        };
+
+        let mut reader = BufReader::new(stderr);
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line).await.unwrap_or(0) > 0 {
+            on_line(String::from_utf8_lossy(&line).trim_end());
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(std::mem::take(&mut line));
+        }
+        tail.into_iter().flatten().collect()
+    };
+
+    let run = async {
+        let ((), stderr) = tokio::join!(read_progress, read_stderr);
+        (child.wait().await, stderr)
+    };
+    let cancelled = async {
+        match cancel {
+            Some(cancel) => cancel.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let finished = tokio::select! {
+        finished = run => Some(finished),
+        _ = cancelled => None,
+    };
+
+    match finished {
+        Some((status, stderr)) => {
+            let status = status.map_err(|e| {
+                VideoError::FFmpegError(format!("Failed to wait for ffmpeg: {}", e))
+            })?;
+            Ok(FfmpegOutput {
+                success: status.success(),
+                stderr,
+            })
+        }
+        None => {
+            // 取消时结束ffmpeg进程并等待其退出
+            let _ = child.kill().await;
+            Err(VideoError::Cancelled)
+        }
+    }
+}
+
+// 把音轨解码为单声道的32位浮点PCM
+pub async fn decode_audio(
+    ffmpeg_path: &str,
+    input: &Path,
+    sample_rate: u32,
+    cancel: Option<&CancelToken>,
+) -> VideoResult<Vec<f32>> {
+    let child = Command::new(ffmpeg_path)
+        .args(["-v", "error", "-i"])
+        .arg(input)
+        .args([
+            "-vn",
+            "-ac",
+            "1",
+            "-ar",
+            &sample_rate.to_string(),
+            "-f",
+            "f32le",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+
+    let cancelled = async {
+        match cancel {
+            Some(cancel) => cancel.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+    let output = tokio::select! {
+        output = child.wait_with_output() => output?,
+        _ = cancelled => return Err(VideoError::Cancelled),
+    };
+
+    if !output.status.success() {
+        return Err(VideoError::FFmpegError(format!(
+            "Failed to decode audio from {}: {}",
+            input.display(),
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
@@ -0,0 +1,480 @@
+use crate::models::*;
+use crate::services::cancel::CancelToken;
+use crate::services::splitter::VideoSplitter;
+use crate::utils::{VideoError, VideoResult};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, WebviewWindow};
+use tokio::sync::watch;
+
+pub const JOB_UPDATED_EVENT: &str = "job-updated";
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: CancelToken,
+    // 从磁盘恢复的任务在用户继续之前没有窗口
+    window: Option<WebviewWindow>,
+}
+
+// 把未完成的任务保存到应用数据目录，应用重启后可以继续
+struct JobStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JobStore {
+    fn load(&self) -> Vec<JobInfo> {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            println!("Failed to parse job store {:?}: {}", self.path, e);
+            Vec::new()
+        })
+    }
+
+    // 调用方需持有 lock
+    fn save(&self, jobs: &[JobInfo]) {
+        let result = serde_json::to_vec_pretty(jobs)
+            .map_err(std::io::Error::other)
+            .and_then(|content| {
+                // 先写临时文件再重命名，避免写到一半时崩溃损坏队列
+                let tmp_path = self.path.with_extension("json.tmp");
+                std::fs::write(&tmp_path, content)?;
+                std::fs::rename(&tmp_path, &self.path)
+            });
+        if let Err(e) = result {
+            println!("Failed to save job store {:?}: {}", self.path, e);
+        }
+    }
+}
+
+// 分割任务队列：按优先级调度，并限制同时运行的任务数
+pub struct JobManager {
+    splitter: Arc<VideoSplitter>,
+    max_concurrent: AtomicUsize,
+    jobs: Mutex<BTreeMap<JobId, JobEntry>>,
+    next_id: Mutex<JobId>,
+    store: JobStore,
+    // 每次任务状态变化时递增，用于等待任务结束
+    updates: watch::Sender<u64>,
+}
+
+impl JobManager {
+    pub fn new(splitter: Arc<VideoSplitter>, max_concurrent: usize, store_path: PathBuf) -> Self {
+        let (updates, _) = watch::channel(0);
+        let store = JobStore {
+            path: store_path,
+            lock: Mutex::new(()),
+        };
+
+        // 上次退出时排队或运行中的任务标记为中断，由用户决定是否继续
+        let mut jobs = BTreeMap::new();
+        for mut info in store.load() {
+            if !info.status.is_finished() {
+                info.status = JobStatus::Interrupted;
+            }
+            println!(
+                "Restored job {} with {}/{} finished segments",
+                info.id,
+                info.completed_segments.len(),
+                info.plan.len()
+            );
+            jobs.insert(
+                info.id,
+                JobEntry {
+                    info,
+                    cancel: CancelToken::new(),
+                    window: None,
+                },
+            );
+        }
+        let next_id = jobs.keys().next_back().map_or(1, |id| id + 1);
+        splitter.share_cores(max_concurrent.max(1));
+
+        Self {
+            splitter,
+            max_concurrent: AtomicUsize::new(max_concurrent.max(1)),
+            jobs: Mutex::new(jobs),
+            next_id: Mutex::new(next_id),
+            store,
+            updates,
+        }
+    }
+
+    pub fn set_max_concurrent(self: &Arc<Self>, max_concurrent: usize) {
+        self.max_concurrent
+            .store(max_concurrent.max(1), Ordering::SeqCst);
+        self.splitter.share_cores(max_concurrent.max(1));
+        self.schedule();
+    }
+
+    pub fn enqueue(
+        self: &Arc<Self>,
+        request: SplitRequest,
+        priority: i32,
+        window: &WebviewWindow,
+    ) -> JobId {
+        let id = {
+            let mut next_id = self.next_id.lock();
+            let id = *next_id;
+            *next_id += 1;
+            id
+        };
+
+        let info = JobInfo {
+            id,
+            status: JobStatus::Queued,
+            priority,
+            request,
+            plan: Vec::new(),
+            completed_segments: Vec::new(),
+            result: None,
+            error: None,
+            created_at: unix_now(),
+            started_at: None,
+            finished_at: None,
+        };
+        println!("Job {} queued with priority {}", id, priority);
+
+        self.jobs.lock().insert(
+            id,
+            JobEntry {
+                info: info.clone(),
+                cancel: CancelToken::new(),
+                window: Some(window.clone()),
+            },
+        );
+        self.notify(Some(window), info);
+        self.schedule();
+
+        id
+    }
+
+    // 从第一个未完成的段继续，之前完成的输出需要仍然完好
+    pub fn resume(self: &Arc<Self>, id: JobId, window: &WebviewWindow) -> Result<JobInfo, String> {
+        let (plan, mut completed, by_size) = {
+            let jobs = self.jobs.lock();
+            let entry = jobs
+                .get(&id)
+                .ok_or_else(|| format!("Job not found: {}", id))?;
+            match entry.info.status {
+                JobStatus::Interrupted | JobStatus::Failed | JobStatus::Cancelled => {}
+                status => return Err(format!("Job {} cannot be resumed while {:?}", id, status)),
+            }
+            (
+                entry.info.plan.clone(),
+                entry.info.completed_segments.clone(),
+                matches!(entry.info.request.split_type, SplitType::Size { .. }),
+            )
+        };
+
+        // 校验需要运行ffprobe，不在持有锁时进行
+        completed.sort_by_key(|s| s.index);
+        let first_unfinished = if by_size {
+            // 按大小分割时实际的分段与估计的计划不一致，从首尾相接的已完成前缀的结尾继续
+            let mut end = 0.0;
+            let prefix = completed
+                .iter()
+                .enumerate()
+                .take_while(|&(i, c)| {
+                    let contiguous = c.index == i
+                        && (c.start - end).abs() < 0.001
+                        && self.splitter.is_output_intact(c);
+                    end = c.end;
+                    contiguous
+                })
+                .count();
+            // 之后的分段会在新的范围重新切分，旧文件不能被当作已有输出保留或阻止写入
+            for stale in &completed[prefix..] {
+                let _ = std::fs::remove_file(&stale.output_file);
+            }
+            prefix
+        } else {
+            plan.iter()
+                .map(|s| s.index)
+                .find(|&index| {
+                    !completed
+                        .iter()
+                        .any(|c| c.index == index && self.splitter.is_output_intact(c))
+                })
+                .unwrap_or(plan.len())
+        };
+        completed.retain(|c| c.index < first_unfinished);
+        println!("Resuming job {} from segment {}", id, first_unfinished + 1);
+
+        let info = {
+            let mut jobs = self.jobs.lock();
+            let entry = jobs
+                .get_mut(&id)
+                .ok_or_else(|| format!("Job not found: {}", id))?;
+            entry.info.status = JobStatus::Queued;
+            entry.info.completed_segments = completed;
+            entry.info.result = None;
+            entry.info.error = None;
+            entry.info.finished_at = None;
+            entry.cancel = CancelToken::new();
+            entry.window = Some(window.clone());
+            entry.info.clone()
+        };
+
+        self.notify(Some(window), info.clone());
+        self.schedule();
+
+        Ok(info)
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.jobs.lock().values().map(|e| e.info.clone()).collect()
+    }
+
+    pub fn get(&self, id: JobId) -> Option<JobInfo> {
+        self.jobs.lock().get(&id).map(|e| e.info.clone())
+    }
+
+    // 排队中的任务直接标记为取消，运行中的任务由分割器自行结束
+    pub fn cancel(&self, id: JobId) -> bool {
+        let mut jobs = self.jobs.lock();
+        let Some(entry) = jobs.get_mut(&id) else {
+            return false;
+        };
+
+        match entry.info.status {
+            JobStatus::Queued => {
+                entry.info.status = JobStatus::Cancelled;
+                entry.info.finished_at = Some(unix_now());
+                entry.info.result = Some(cancelled_result(&entry.info));
+                let (window, info) = (entry.window.clone(), entry.info.clone());
+                drop(jobs);
+                self.notify(window.as_ref(), info);
+                true
+            }
+            JobStatus::Running => {
+                entry.cancel.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn cancel_running(&self) -> usize {
+        let running: Vec<JobId> = self
+            .jobs
+            .lock()
+            .values()
+            .filter(|e| e.info.status == JobStatus::Running)
+            .map(|e| e.info.id)
+            .collect();
+        running.into_iter().filter(|&id| self.cancel(id)).count()
+    }
+
+    pub fn remove(&self, id: JobId) -> bool {
+        self.cancel(id);
+        let removed = self.jobs.lock().remove(&id).is_some();
+        if removed {
+            self.persist();
+            self.updates.send_modify(|v| *v += 1);
+        }
+        removed
+    }
+
+    // 等待任务结束，任务被删除时返回None
+    pub async fn wait(&self, id: JobId) -> Option<JobInfo> {
+        let mut updates = self.updates.subscribe();
+        loop {
+            match self.get(id) {
+                Some(info) if info.status.is_finished() => return Some(info),
+                None => return None,
+                _ => {}
+            }
+            if updates.changed().await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    fn schedule(self: &Arc<Self>) {
+        let mut started = Vec::new();
+        {
+            let mut jobs = self.jobs.lock();
+            let limit = self.max_concurrent.load(Ordering::SeqCst);
+            let mut running = jobs
+                .values()
+                .filter(|e| e.info.status == JobStatus::Running)
+                .count();
+
+            while running < limit {
+                // 优先级高的先运行，同优先级按入队顺序
+                let next = jobs
+                    .values()
+                    .filter(|e| e.info.status == JobStatus::Queued && e.window.is_some())
+                    .min_by_key(|e| (-e.info.priority, e.info.id))
+                    .map(|e| e.info.id);
+                let Some(id) = next else { break };
+
+                let entry = jobs.get_mut(&id).unwrap();
+                entry.info.status = JobStatus::Running;
+                entry.info.started_at = Some(unix_now());
+                if let Some(window) = entry.window.clone() {
+                    started.push((entry.info.clone(), entry.cancel.clone(), window));
+                }
+                running += 1;
+            }
+        }
+
+        for (info, cancel, window) in started {
+            println!("Job {} started", info.id);
+            self.notify(Some(&window), info.clone());
+
+            let manager = Arc::clone(self);
+            tauri::async_runtime::spawn(async move {
+                let id = info.id;
+                let result = manager.run(info, &window, &cancel).await;
+                manager.finish(id, result);
+            });
+        }
+    }
+
+    async fn run(
+        self: &Arc<Self>,
+        info: JobInfo,
+        window: &WebviewWindow,
+        cancel: &CancelToken,
+    ) -> VideoResult<SplitResult> {
+        let id = info.id;
+        let start_time = std::time::Instant::now();
+
+        // 先保存分割计划，之后每完成一段都会记录下来
+        let plan = if info.plan.is_empty() {
+            let plan = self
+                .splitter
+                .plan_segments(&info.request, window, cancel)
+                .await?;
+            self.update(id, |job| job.plan = plan.clone());
+            plan
+        } else {
+            info.plan
+        };
+
+        let manager = Arc::clone(self);
+        let on_segment_done = move |segment: &SegmentResult| {
+            manager.update(id, |job| job.completed_segments.push(segment.clone()));
+        };
+
+        let mut result = self
+            .splitter
+            .execute_plan(
+                &info.request,
+                &plan,
+                info.completed_segments,
+                window,
+                cancel,
+                &on_segment_done,
+            )
+            .await?;
+        result.processing_time = start_time.elapsed().as_secs_f64();
+
+        Ok(result)
+    }
+
+    fn update<F>(&self, id: JobId, f: F)
+    where
+        F: FnOnce(&mut JobInfo),
+    {
+        let updated = {
+            let mut jobs = self.jobs.lock();
+            jobs.get_mut(&id).map(|entry| {
+                f(&mut entry.info);
+                (entry.window.clone(), entry.info.clone())
+            })
+        };
+        if let Some((window, info)) = updated {
+            self.notify(window.as_ref(), info);
+        }
+    }
+
+    fn finish(self: &Arc<Self>, id: JobId, result: VideoResult<SplitResult>) {
+        self.update(id, |job| {
+            match result {
+                Ok(result) => {
+                    // 有分段失败的任务标记为失败，之后可以继续处理剩余的分段
+                    job.status = if result.cancelled {
+                        JobStatus::Cancelled
+                    } else if result.success {
+                        JobStatus::Completed
+                    } else {
+                        job.error = Some(result.errors.join("\n"));
+                        JobStatus::Failed
+                    };
+                    job.result = Some(result);
+                }
+                // 排队或规划阶段被取消时没有分割结果，用已完成的分段构造一个
+                Err(VideoError::Cancelled) => {
+                    job.status = JobStatus::Cancelled;
+                    job.result = Some(cancelled_result(job));
+                }
+                Err(e) => {
+                    println!("Job {} failed: {}", id, e);
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+            }
+            job.finished_at = Some(unix_now());
+            println!("Job {} finished with status {:?}", id, job.status);
+        });
+        self.schedule();
+    }
+
+    // 只保存尚未完成的任务
+    // 持有保存锁时才取快照，否则同时完成的分段可能让较旧的快照覆盖较新的
+    fn persist(&self) {
+        let _guard = self.store.lock.lock();
+        let unfinished: Vec<JobInfo> = self
+            .jobs
+            .lock()
+            .values()
+            .filter(|e| !e.info.status.is_finished())
+            .map(|e| e.info.clone())
+            .collect();
+        self.store.save(&unfinished);
+    }
+
+    fn notify(&self, window: Option<&WebviewWindow>, info: JobInfo) {
+        self.persist();
+        self.updates.send_modify(|v| *v += 1);
+        let Some(window) = window else { return };
+        if let Err(e) = window.emit(JOB_UPDATED_EVENT, info) {
+            println!("Failed to emit job event: {}", e);
+        }
+    }
+}
+
+fn cancelled_result(job: &JobInfo) -> SplitResult {
+    let segments = job.completed_segments.clone();
+    SplitResult {
+        success: false,
+        cancelled: true,
+        output_files: segments
+            .iter()
+            .filter(|s| s.status == SegmentStatus::Completed)
+            .map(|s| s.output_file.clone())
+            .collect(),
+        errors: Vec::new(),
+        processing_time: job
+            .started_at
+            .map(|started| unix_now().saturating_sub(started) as f64)
+            .unwrap_or(0.0),
+        segments,
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,2 +1,10 @@
 pub mod video_processor;
-pub mod splitter;
\ No newline at end of file
+pub mod splitter;
+pub mod ffmpeg;
+pub mod progress;
+pub mod cancel;
+pub mod job_manager;
+pub mod subtitles;
+pub mod audio_match;
+pub mod scene_detector;
+pub mod scene_cache;
\ No newline at end of file
@@ -0,0 +1,134 @@
+use crate::models::ProgressInfo;
+use crate::services::ffmpeg::FfmpegProgress;
+use parking_lot::Mutex;
+use std::time::Instant;
+use tauri::{Emitter, WebviewWindow};
+
+pub const SPLIT_PROGRESS_EVENT: &str = "split-progress";
+pub const SCENE_PROGRESS_EVENT: &str = "scene-detection-progress";
+pub const ANALYSIS_PROGRESS_EVENT: &str = "analysis-progress";
+
+// 发送单个分析任务（场景、静音检测等）的进度
+pub fn emit_analysis_progress(
+    window: &WebviewWindow,
+    event: &str,
+    message: &str,
+    duration: f64,
+    progress: &FfmpegProgress,
+) {
+    let percentage = if duration > 0.0 {
+        (progress.out_time / duration * 100.0).min(100.0)
+    } else {
+        0.0
+    };
+    let eta = progress
+        .speed
+        .filter(|&speed| speed > 0.0)
+        .map(|speed| (duration - progress.out_time).max(0.0) / speed);
+    let info = ProgressInfo {
+        current: 1,
+        total: 1,
+        message: message.to_string(),
+        percentage,
+        segment_percentage: percentage,
+        speed: progress.speed,
+        eta,
+    };
+    if let Err(e) = window.emit(event, info) {
+        println!("Failed to emit progress event: {}", e);
+    }
+}
+
+// 汇总所有并行分段的进度，并通过Tauri事件发送给前端
+pub struct SplitProgress {
+    window: WebviewWindow,
+    segments: Mutex<Segments>,
+    started: Instant,
+}
+
+struct Segments {
+    durations: Vec<f64>,
+    processed: Vec<f64>,
+}
+
+impl SplitProgress {
+    pub fn new(window: &WebviewWindow, durations: Vec<f64>) -> Self {
+        let processed = vec![0.0; durations.len()];
+        Self {
+            window: window.clone(),
+            segments: Mutex::new(Segments {
+                durations,
+                processed,
+            }),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn total_segments(&self) -> usize {
+        self.segments.lock().durations.len()
+    }
+
+    // 分割点在处理过程中调整时更新该段时长，必要时追加新段
+    pub fn set_segment_duration(&self, segment_index: usize, duration: f64) {
+        let mut segments = self.segments.lock();
+        if segments.durations.len() <= segment_index {
+            segments.durations.resize(segment_index + 1, 0.0);
+            segments.processed.resize(segment_index + 1, 0.0);
+        }
+        segments.durations[segment_index] = duration;
+    }
+
+    // out_time 为该段已处理的时长（秒）
+    pub fn update(&self, segment_index: usize, out_time: f64, speed: Option<f64>) {
+        let (segment_duration, total_segments, overall) = {
+            let mut segments = self.segments.lock();
+            let Some(&segment_duration) = segments.durations.get(segment_index) else {
+                return;
+            };
+            segments.processed[segment_index] = out_time.clamp(0.0, segment_duration);
+            let total: f64 = segments.durations.iter().sum();
+            let overall = if total > 0.0 {
+                segments.processed.iter().sum::<f64>() / total
+            } else {
+                0.0
+            };
+            (segment_duration, segments.durations.len(), overall)
+        };
+        let out_time = out_time.clamp(0.0, segment_duration);
+
+        let segment_percentage = if segment_duration > 0.0 {
+            out_time / segment_duration * 100.0
+        } else {
+            100.0
+        };
+
+        // 并行处理时按已用时间推算剩余时间
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta = (overall > 0.0).then(|| elapsed * (1.0 - overall) / overall);
+
+        let info = ProgressInfo {
+            current: segment_index as u32 + 1,
+            total: total_segments as u32,
+            message: format!(
+                "Processing segment {} of {}",
+                segment_index + 1,
+                total_segments
+            ),
+            percentage: overall * 100.0,
+            segment_percentage,
+            speed,
+            eta,
+        };
+
+        if let Err(e) = self.window.emit(SPLIT_PROGRESS_EVENT, info) {
+            println!("Failed to emit progress event: {}", e);
+        }
+    }
+
+    pub fn finish_segment(&self, segment_index: usize) {
+        let duration = self.segments.lock().durations.get(segment_index).copied();
+        if let Some(duration) = duration {
+            self.update(segment_index, duration, None);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::models::FrameScore;
+use crate::utils::VideoResult;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// 计算内容哈希时每次读取的字节数
+const READ_CHUNK: usize = 1024 * 1024;
+// 已计算的文件哈希保存在缓存目录中的这个文件里
+const HASHES_FILE: &str = "file-hashes.json";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// 已计算过的内容哈希，文件大小和修改时间都没变时不再重新读取
+#[derive(Serialize, Deserialize)]
+struct FileHash {
+    size: u64,
+    modified: SystemTime,
+    hash: u64,
+}
+
+// 把逐帧的场景分数缓存到磁盘，检测和分割以及之后的会话都可以直接复用
+pub struct SceneCache {
+    dir: PathBuf,
+    // 以规范化路径为键，与分数缓存一起保存，重启后也不必重新读取整个文件
+    hashes: Mutex<HashMap<PathBuf, FileHash>>,
+}
+
+impl SceneCache {
+    pub fn new(dir: PathBuf) -> Self {
+        let hashes = std::fs::read(dir.join(HASHES_FILE))
+            .ok()
+            .and_then(|content| {
+                serde_json::from_slice(&content)
+                    .map_err(|e| println!("Failed to parse {}: {}", HASHES_FILE, e))
+                    .ok()
+            })
+            .unwrap_or_default();
+        Self {
+            dir,
+            hashes: Mutex::new(hashes),
+        }
+    }
+
+    // 缓存键由整个文件内容的哈希和分析参数组成，文件被移动或改名后仍然命中
+    // 文件大小和修改时间没变时直接使用记录的哈希，否则读取整个文件，应在阻塞线程中调用
+    pub fn key(&self, video_path: &Path, analysis: &str) -> VideoResult<String> {
+        let content = self.content_hash(video_path)?;
+        let analysis = fnv1a(FNV_OFFSET, analysis.as_bytes());
+        Ok(format!("{:016x}-{:016x}", content, analysis))
+    }
+
+    fn content_hash(&self, video_path: &Path) -> VideoResult<u64> {
+        let path = std::fs::canonicalize(video_path)?;
+        let metadata = std::fs::metadata(&path)?;
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+        if let Some(cached) = self.hashes.lock().get(&path) {
+            if cached.size == size && Some(cached.modified) == modified {
+                return Ok(cached.hash);
+            }
+        }
+
+        println!("Hashing {:?} for the scene cache", path);
+        let mut file = File::open(&path)?;
+        let mut buffer = vec![0u8; READ_CHUNK];
+        let mut hash = FNV_OFFSET;
+        loop {
+            let read = read_full(&mut file, &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hash = fnv1a_words(hash, &buffer[..read]);
+        }
+
+        // 取不到修改时间的文件系统上无法判断文件是否变化，不记录
+        if let Some(modified) = modified {
+            let mut hashes = self.hashes.lock();
+            hashes.insert(
+                path,
+                FileHash {
+                    size,
+                    modified,
+                    hash,
+                },
+            );
+            // 持有锁时写入，避免较旧的记录覆盖较新的
+            let result = serde_json::to_vec(&*hashes)
+                .map_err(std::io::Error::other)
+                .and_then(|content| self.write(&self.dir.join(HASHES_FILE), content));
+            if let Err(e) = result {
+                println!("Failed to save {}: {}", HASHES_FILE, e);
+            }
+        }
+        Ok(hash)
+    }
+
+    pub fn load(&self, key: &str) -> Option<Vec<FrameScore>> {
+        let content = std::fs::read(self.path(key)).ok()?;
+        serde_json::from_slice(&content)
+            .map_err(|e| println!("Failed to parse scene cache {}: {}", key, e))
+            .ok()
+    }
+
+    // 写入失败只影响下次的速度，因此只记录日志
+    pub fn store(&self, key: &str, scores: &[FrameScore]) {
+        let path = self.path(key);
+        let result = serde_json::to_vec(scores)
+            .map_err(std::io::Error::other)
+            .and_then(|content| self.write(&path, content));
+        if let Err(e) = result {
+            println!("Failed to save scene cache {:?}: {}", path, e);
+        }
+    }
+
+    // 先写临时文件再重命名，避免同时分析同一文件时读到一半的缓存
+    fn write(&self, path: &Path, content: Vec<u8>) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+// 尽量读满缓冲区，使按8字节分组的哈希不受每次读取长度的影响
+fn read_full(file: &mut File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+// FNV-1a 的变体，每次处理8个字节，读取大文件时比逐字节快得多
+fn fnv1a_words(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut words = bytes.chunks_exact(8);
+    for word in &mut words {
+        hash ^= u64::from_le_bytes(word.try_into().unwrap());
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    fnv1a(hash, words.remainder())
+}
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_is_remembered_across_instances_and_follows_content() {
+        let dir = std::env::temp_dir().join(format!("scene-cache-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("video.mp4");
+        std::fs::write(&video, vec![7u8; 3 * READ_CHUNK + 5]).unwrap();
+
+        let key = SceneCache::new(dir.clone()).key(&video, "a").unwrap();
+        // 新的实例从保存的记录中取得哈希
+        let reloaded = SceneCache::new(dir.clone());
+        assert!(reloaded
+            .hashes
+            .lock()
+            .contains_key(&std::fs::canonicalize(&video).unwrap()));
+        assert_eq!(reloaded.key(&video, "a").unwrap(), key);
+        assert_ne!(reloaded.key(&video, "b").unwrap(), key);
+
+        std::fs::write(&video, vec![8u8; 3 * READ_CHUNK + 6]).unwrap();
+        let changed = SceneCache::new(dir.clone()).key(&video, "a").unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_ne!(changed, key);
+    }
+}
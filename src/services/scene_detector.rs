@@ -0,0 +1,450 @@
+use crate::models::{FrameScore, ScenePoint, TransitionType};
+use crate::services::cancel::CancelToken;
+use crate::services::ffmpeg::FfmpegProgress;
+use crate::services::progress::{emit_analysis_progress, SCENE_PROGRESS_EVENT};
+use crate::utils::{value_after, VideoError, VideoResult};
+use image::{GrayImage, RgbImage};
+use std::path::Path;
+use std::process::Stdio;
+use tauri::WebviewWindow;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+// 分析时把画面缩小到固定尺寸，统计量对分辨率不敏感
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 90;
+const FRAME_BYTES: usize = (WIDTH * HEIGHT * 3) as usize;
+// 等待打分的帧数上限，读取快于打分时暂停读取
+const FRAME_QUEUE: usize = 32;
+
+const HUE_BINS: usize = 16;
+const SATURATION_BINS: usize = 4;
+const VALUE_BINS: usize = 4;
+const HSV_WEIGHT: f32 = 1.0;
+const EDGE_WEIGHT: f32 = 0.5;
+// 拉普拉斯响应超过该值的像素视为边缘
+const EDGE_THRESHOLD: u8 = 32;
+const LAPLACIAN: [f32; 9] = [-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0];
+
+// 本地分析的参数，修改尺寸或打分方式时需要同时修改，使旧缓存失效
+pub const ANALYSIS: &str = "content-160x90-v1";
+
+// 平均亮度低于该值的帧视为黑场
+const DARK_LUMA: f32 = 0.08;
+// 黑场前后 window 帧的平均亮度至少比黑场高出这么多，才算淡出或淡入
+const FADE_MIN_DROP: f32 = 0.1;
+// 叠化期间平滑后的分数至少是周围中位数的这么多倍
+const DISSOLVE_MIN_RATIO: f32 = 2.0;
+// 叠化期间分数的总和，太小说明前后画面几乎没有变化
+const DISSOLVE_MIN_CHANGE: f32 = 0.25;
+// 计算中位数时分数的下限，避免静止画面中的噪声被放大
+const MIN_BASELINE: f32 = 0.005;
+
+// 单帧的统计信息，与上一帧比较得到分数
+struct FrameStats {
+    hue: [f32; HUE_BINS],
+    saturation: [f32; SATURATION_BINS],
+    value: [f32; VALUE_BINS],
+    edges: Vec<bool>,
+    luma: f32,
+}
+
+// 从ffmpeg的rawvideo管道读取缩小后的帧，按HSV直方图和边缘的变化给每帧打分
+// 帧号和PTS来自同时输出到stderr的showinfo
+pub async fn analyze_frames(
+    ffmpeg_path: &str,
+    video_path: &Path,
+    fps: f64,
+    duration: f64,
+    window: Option<&WebviewWindow>,
+    cancel: Option<&CancelToken>,
+) -> VideoResult<Vec<FrameScore>> {
+    let mut child = Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-nostats", "-i"])
+        .arg(video_path)
+        .args([
+            "-an",
+            "-vf",
+            &format!("scale={}:{},showinfo", WIDTH, HEIGHT),
+            "-pix_fmt",
+            "rgb24",
+            // 保持输入的帧，否则可变帧率的视频会被补帧或丢帧，与showinfo的记录对不上
+            "-fps_mode",
+            "passthrough",
+            "-f",
+            "rawvideo",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    // 打分是CPU密集的计算，放到阻塞线程中进行，异步任务只负责读取管道
+    let (frames_tx, mut frames_rx) = mpsc::channel::<Vec<u8>>(FRAME_QUEUE);
+    let scoring = tokio::task::spawn_blocking(move || {
+        let mut scores = Vec::new();
+        let mut previous: Option<FrameStats> = None;
+        while let Some(buffer) = frames_rx.blocking_recv() {
+            let Some(image) = RgbImage::from_raw(WIDTH, HEIGHT, buffer) else {
+                break;
+            };
+            let stats = frame_stats(&image);
+            let score = match &previous {
+                Some(previous) => content_score(previous, &stats),
+                None => 0.0,
+            };
+            scores.push((score, stats.luma));
+            previous = Some(stats);
+        }
+        scores
+    });
+
+    let read_frames = async move {
+        let Some(mut stdout) = stdout else {
+            return;
+        };
+        let mut frames = 0usize;
+        loop {
+            let mut buffer = vec![0u8; FRAME_BYTES];
+            if stdout.read_exact(&mut buffer).await.is_err()
+                || frames_tx.send(buffer).await.is_err()
+            {
+                break;
+            }
+            frames += 1;
+
+            if let Some(window) = window {
+                if fps > 0.0 && frames.is_multiple_of(10) {
+                    let progress = FfmpegProgress {
+                        out_time: frames as f64 / fps,
+                        speed: None,
+                    };
+                    emit_analysis_progress(
+                        window,
+                        SCENE_PROGRESS_EVENT,
+                        "Analyzing frames",
+                        duration,
+                        &progress,
+                    );
+                }
+            }
+        }
+    };
+
+    // showinfo 每帧输出一行 n:、pts: 和 pts_time:
+    let read_timestamps = async {
+        let mut timestamps = Vec::new();
+        let mut errors = String::new();
+        let Some(stderr) = stderr else {
+            return (timestamps, errors);
+        };
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if !line.contains("showinfo") {
+                errors.push_str(&line);
+                errors.push('\n');
+                continue;
+            }
+            let frame = value_after(&line, " n:");
+            let pts = value_after(&line, " pts:");
+            let time = value_after(&line, "pts_time:");
+            if let (Some(frame), Some(pts), Some(time)) = (frame, pts, time) {
+                timestamps.push((frame as u64, pts as i64, time));
+            }
+        }
+        (timestamps, errors)
+    };
+
+    let run = async {
+        let ((), (timestamps, errors)) = tokio::join!(read_frames, read_timestamps);
+        (child.wait().await, timestamps, errors)
+    };
+    let cancelled = async {
+        match cancel {
+            Some(cancel) => cancel.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let (status, timestamps, errors) = tokio::select! {
+        finished = run => finished,
+        _ = cancelled => return Err(VideoError::Cancelled),
+    };
+    if !status?.success() {
+        return Err(VideoError::FFmpegError(format!(
+            "Frame analysis failed: {}",
+            errors
+        )));
+    }
+    let scores = scoring
+        .await
+        .map_err(|e| VideoError::ProcessingError(format!("Frame analysis failed: {}", e)))?;
+
+    Ok(scores
+        .into_iter()
+        .zip(timestamps)
+        .map(|((score, luma), (frame_number, pts, time))| FrameScore {
+            frame_number,
+            pts,
+            time,
+            score,
+            luma,
+        })
+        .collect())
+}
+
+// 分数与前后 window 帧的平均分之比不低于 threshold，且分数不低于 min_score 的帧作为切点
+// 镜头晃动等持续的高分会抬高平均值，因此不会被误判
+pub fn adaptive_scenes(
+    scores: &[FrameScore],
+    threshold: f32,
+    window: usize,
+    min_score: f32,
+    min_duration: f64,
+) -> Vec<ScenePoint> {
+    let window = window.max(1);
+    let mut scenes = Vec::new();
+    let mut last_time = 0.0;
+
+    for (i, frame) in scores.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(scores.len());
+        let neighbours: Vec<f32> = scores[start..end]
+            .iter()
+            .enumerate()
+            .filter(|&(j, _)| start + j != i)
+            .map(|(_, f)| f.score)
+            .collect();
+        if neighbours.is_empty() {
+            continue;
+        }
+        let average = neighbours.iter().sum::<f32>() / neighbours.len() as f32;
+        let ratio = if average > 0.0 {
+            frame.score / average
+        } else {
+            f32::INFINITY
+        };
+
+        if frame.score >= min_score && ratio >= threshold && frame.time - last_time >= min_duration
+        {
+            scenes.push(ScenePoint {
+                time: frame.time,
+                pts: frame.pts,
+                confidence: frame.score,
+                frame_number: frame.frame_number,
+                transition: TransitionType::Cut,
+            });
+            last_time = frame.time;
+        }
+    }
+
+    scenes
+}
+
+// 识别硬切、淡出淡入和叠化，threshold 为硬切的分数阈值
+// 淡出淡入总是成对报告，min_duration 只用于不同转场之间
+pub fn transition_scenes(
+    scores: &[FrameScore],
+    threshold: f32,
+    window: usize,
+    min_duration: f64,
+) -> Vec<ScenePoint> {
+    let window = window.max(2);
+    let point = |frame: &FrameScore, confidence: f32, transition| ScenePoint {
+        time: frame.time,
+        pts: frame.pts,
+        confidence: confidence.clamp(0.0, 1.0),
+        frame_number: frame.frame_number,
+        transition,
+    };
+    let mean_luma = |frames: &[FrameScore]| {
+        frames.iter().map(|f| f.luma).sum::<f32>() / frames.len().max(1) as f32
+    };
+
+    let mut found: Vec<ScenePoint> = Vec::new();
+    // 淡入淡出附近的分数变化来自亮度，不再参与硬切和叠化的判断
+    let mut in_fade = vec![false; scores.len()];
+
+    let mut i = 0;
+    while i < scores.len() {
+        if scores[i].luma >= DARK_LUMA {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < scores.len() && scores[i].luma < DARK_LUMA {
+            i += 1;
+        }
+        let end = i;
+
+        let before = mean_luma(&scores[start.saturating_sub(window)..start]);
+        let after = mean_luma(&scores[end..(end + window).min(scores.len())]);
+        let fade_out = start > 0 && before - scores[start].luma >= FADE_MIN_DROP;
+        let fade_in = end < scores.len() && after - scores[end - 1].luma >= FADE_MIN_DROP;
+        if fade_out {
+            found.push(point(
+                &scores[start],
+                before - scores[start].luma,
+                TransitionType::FadeOut,
+            ));
+        }
+        if fade_in {
+            found.push(point(
+                &scores[end],
+                after - scores[end - 1].luma,
+                TransitionType::FadeIn,
+            ));
+        }
+        if fade_out || fade_in {
+            let from = start.saturating_sub(window);
+            let to = (end + window).min(scores.len());
+            in_fade[from..to].fill(true);
+        }
+    }
+
+    for (frame, &fading) in scores.iter().zip(&in_fade) {
+        if !fading && frame.score > threshold {
+            found.push(point(frame, frame.score, TransitionType::Cut));
+        }
+    }
+
+    // 叠化时相邻帧的差异持续偏高，但没有单帧达到硬切的程度
+    let half = window / 2;
+    let elevated: Vec<bool> = (0..scores.len())
+        .map(|k| {
+            if in_fade[k] {
+                return false;
+            }
+            let nearby = &scores[k.saturating_sub(half)..(k + half + 1).min(scores.len())];
+            let smoothed = nearby.iter().map(|f| f.score).sum::<f32>() / nearby.len() as f32;
+
+            let mut surrounding: Vec<f32> = scores
+                [k.saturating_sub(window * 4)..(k + window * 4 + 1).min(scores.len())]
+                .iter()
+                .map(|f| f.score)
+                .collect();
+            surrounding.sort_by(f32::total_cmp);
+            let baseline = surrounding[surrounding.len() / 2].max(MIN_BASELINE);
+
+            smoothed >= baseline * DISSOLVE_MIN_RATIO
+        })
+        .collect();
+
+    let mut k = 0;
+    while k < scores.len() {
+        if !elevated[k] {
+            k += 1;
+            continue;
+        }
+        let start = k;
+        while k < scores.len() && elevated[k] {
+            k += 1;
+        }
+        let run = &scores[start..k];
+        let change: f32 = run.iter().map(|f| f.score).sum();
+        let peak = run.iter().map(|f| f.score).fold(0.0, f32::max);
+        if run.len() >= window && change >= DISSOLVE_MIN_CHANGE && peak <= threshold {
+            found.push(point(&run[run.len() / 2], change, TransitionType::Dissolve));
+        }
+    }
+
+    found.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut scenes: Vec<ScenePoint> = Vec::new();
+    let mut last_time = 0.0;
+    for scene in found {
+        let paired = scene.transition == TransitionType::FadeIn
+            && scenes
+                .last()
+                .is_some_and(|last| last.transition == TransitionType::FadeOut);
+        if paired || scene.time - last_time >= min_duration {
+            last_time = scene.time;
+            scenes.push(scene);
+        }
+    }
+
+    scenes
+}
+
+fn frame_stats(image: &RgbImage) -> FrameStats {
+    let mut hue = [0.0; HUE_BINS];
+    let mut saturation = [0.0; SATURATION_BINS];
+    let mut value = [0.0; VALUE_BINS];
+    let weight = 1.0 / (WIDTH * HEIGHT) as f32;
+
+    for pixel in image.pixels() {
+        let (h, s, v) = to_hsv(pixel.0);
+        hue[((h / 360.0 * HUE_BINS as f32) as usize).min(HUE_BINS - 1)] += weight;
+        saturation[((s * SATURATION_BINS as f32) as usize).min(SATURATION_BINS - 1)] += weight;
+        value[((v * VALUE_BINS as f32) as usize).min(VALUE_BINS - 1)] += weight;
+    }
+
+    let gray: GrayImage = image::imageops::grayscale(image);
+    let luma = gray.pixels().map(|p| p.0[0] as f32).sum::<f32>() * weight / 255.0;
+    let edges = image::imageops::filter3x3(&gray, &LAPLACIAN)
+        .pixels()
+        .map(|p| p.0[0] > EDGE_THRESHOLD)
+        .collect();
+
+    FrameStats {
+        hue,
+        saturation,
+        value,
+        edges,
+        luma,
+    }
+}
+
+// 0表示两帧相同，1表示完全不同
+fn content_score(previous: &FrameStats, current: &FrameStats) -> f32 {
+    let histogram_diff =
+        |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f32>() / 2.0;
+    let hsv = (histogram_diff(&previous.hue, &current.hue)
+        + histogram_diff(&previous.saturation, &current.saturation)
+        + histogram_diff(&previous.value, &current.value))
+        / 3.0;
+
+    // 边缘位置发生变化的像素占所有边缘像素的比例
+    let (changed, total) = previous
+        .edges
+        .iter()
+        .zip(&current.edges)
+        .fold((0usize, 0usize), |(changed, total), (&a, &b)| {
+            (changed + (a != b) as usize, total + (a || b) as usize)
+        });
+    let edge = if total > 0 {
+        changed as f32 / total as f32
+    } else {
+        0.0
+    };
+
+    (HSV_WEIGHT * hsv + EDGE_WEIGHT * edge) / (HSV_WEIGHT + EDGE_WEIGHT)
+}
+
+// 返回色相（0到360）、饱和度和明度（0到1）
+fn to_hsv([r, g, b]: [u8; 3]) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+    (hue, saturation, max)
+}
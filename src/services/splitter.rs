@@ -1,22 +1,76 @@
 use crate::models::*;
-use crate::utils::{validate_video_file, VideoError, VideoResult};
+use crate::services::cancel::CancelToken;
+use crate::services::ffmpeg::{run_with_progress, FfmpegProgress};
+use crate::services::progress::SplitProgress;
+use crate::services::subtitles::{self, SubtitleCue};
+use crate::services::video_processor::VideoProcessor;
+use crate::utils::{cpu_core_count, validate_video_file, VideoError, VideoResult};
+use futures::stream::{self, StreamExt};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 use tauri::WebviewWindow;
 
+// 计划分割的一段范围，标题来自章节等
+#[derive(Clone)]
+struct PlannedRange {
+    start: f64,
+    end: f64,
+    title: Option<String>,
+}
+
+// 源视频的关键帧与智能渲染编码参数，每次分割只探测一次
+struct SourceProfile {
+    keyframes: Vec<f64>,
+    smart_encoder: Option<Vec<String>>,
+    // 每个ffmpeg进程可用的线程数
+    threads: usize,
+    // 源文件中的流类型，校验时要求输出包含相同的流
+    stream_types: Vec<String>,
+}
+
+// 单次分割任务在各段之间共享的状态
+struct SplitRun<'a> {
+    request: &'a SplitRequest,
+    source: SourceProfile,
+    progress: SplitProgress,
+    cancel: &'a CancelToken,
+    on_segment_done: &'a (dyn Fn(&SegmentResult) + Send + Sync),
+    // 按 FailurePolicy::Stop 停止后不再开始新的分段
+    halted: AtomicBool,
+}
+
 pub struct VideoSplitter {
     ffmpeg_path: String,
+    ffprobe_path: String,
+    // 与命令共用，场景分数等分析结果的缓存对两边都有效
+    processor: Arc<VideoProcessor>,
+    // 每个任务可用的CPU核数，同时运行多个任务时由任务队列平分
+    cores: AtomicUsize,
 }
 
 impl VideoSplitter {
-    pub fn new() -> Self {
+    pub fn new(processor: Arc<VideoProcessor>) -> Self {
         let ffmpeg_path = Self::find_ffmpeg_path();
+        let ffprobe_path = Self::find_ffprobe_path();
         println!("VideoSplitter using ffmpeg path: {}", ffmpeg_path);
+        println!("VideoSplitter using ffprobe path: {}", ffprobe_path);
         
         Self {
             ffmpeg_path,
+            ffprobe_path,
+            processor,
+            cores: AtomicUsize::new(cpu_core_count()),
         }
     }
+
+    // 按同时运行的任务数平分CPU核数，避免每个任务都按全部核数启动ffmpeg进程
+    pub fn share_cores(&self, concurrent_jobs: usize) {
+        let cores = (cpu_core_count() / concurrent_jobs.max(1)).max(1);
+        self.cores.store(cores, Ordering::SeqCst);
+    }
     
     fn find_ffmpeg_path() -> String {
         let possible_paths = vec![
@@ -62,15 +116,15 @@ impl VideoSplitter {
         "ffprobe".to_string() // 默认回退
     }
 
-    pub async fn split_video(
+    // 计算分割段但不进行编码，结果可以保存下来以便之后继续执行
+    pub async fn plan_segments(
         &self,
-        request: SplitRequest,
+        request: &SplitRequest,
         window: &WebviewWindow,
-    ) -> VideoResult<SplitResult> {
+        cancel: &CancelToken,
+    ) -> VideoResult<Vec<SegmentPlan>> {
         validate_video_file(&request.video_path)?;
 
-        let start_time = std::time::Instant::now();
-
         let split_points = match &request.split_type {
             SplitType::Time { duration, count } => {
                 self.generate_time_splits(&request.video_path, *duration, *count)?
@@ -78,43 +132,948 @@ impl VideoSplitter {
             SplitType::Scenes {
                 threshold,
                 min_duration,
+                detector,
+                group,
             } => {
-                // 使用视频处理器进行场景检测
-                let video_processor = crate::services::video_processor::VideoProcessor::new();
-                let scenes = video_processor
+                // 使用共享的视频处理器进行场景检测，已有缓存时不再分析
+                let scenes = self
+                    .processor
                     .detect_scenes(
                         &request.video_path.to_string_lossy(),
+                        detector,
                         *threshold,
                         min_duration.unwrap_or(2.0),
+                        Some(window),
+                        Some(cancel),
                     )
                     .await?;
 
-                // 提取场景时间点
-                scenes.into_iter().map(|scene| scene.time).collect()
+                // 提取场景时间点，成对的淡出淡入只在淡入处切分，黑场留在前一段末尾
+                let points: Vec<f64> = scenes
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, scene)| {
+                        scene.transition != TransitionType::FadeOut
+                            || scenes
+                                .get(i + 1)
+                                .is_none_or(|next| next.transition != TransitionType::FadeIn)
+                    })
+                    .map(|(_, scene)| scene.time)
+                    .collect();
+
+                // 把相邻场景合并为长度在范围内的段
+                match group {
+                    Some(group) => {
+                        let total_duration = self.get_video_duration(&request.video_path)?;
+                        window_splits(&points, total_duration, group.min_length, group.max_length)?
+                    }
+                    None => points,
+                }
             }
             SplitType::Manual {
                 split_points: manual_points,
+                ..
             } => manual_points.clone(),
+            // 章节直接给出各段的范围
+            SplitType::Chapters { indices } => {
+                let (ranges, total_duration) = self
+                    .chapter_ranges(&request.video_path, indices.as_deref())
+                    .await?;
+                return self.build_segments(request, ranges, total_duration);
+            }
+            SplitType::Size { max_bytes } => {
+                self.generate_size_splits(&request.video_path, *max_bytes)
+                    .await?
+            }
+            SplitType::Silence {
+                noise_db,
+                min_silence,
+                min_length,
+                max_length,
+            } => {
+                let silences = self
+                    .processor
+                    .detect_silence(
+                        &request.video_path.to_string_lossy(),
+                        *noise_db,
+                        *min_silence,
+                        Some(window),
+                        Some(cancel),
+                    )
+                    .await?;
+                let total_duration = self.get_video_duration(&request.video_path)?;
+                // 在静音中间切分
+                let candidates: Vec<f64> =
+                    silences.iter().map(|s| (s.start + s.end) / 2.0).collect();
+                window_splits(&candidates, total_duration, *min_length, *max_length)?
+            }
+            SplitType::BlackFrames {
+                pixel_threshold,
+                picture_threshold,
+                min_duration,
+                drop_black,
+            } => {
+                let blacks = self
+                    .processor
+                    .detect_black_frames(
+                        &request.video_path.to_string_lossy(),
+                        *pixel_threshold,
+                        *picture_threshold,
+                        *min_duration,
+                        Some(window),
+                        Some(cancel),
+                    )
+                    .await?;
+                if blacks.is_empty() {
+                    return Err(VideoError::ProcessingError(
+                        "No black frames detected".to_string(),
+                    ));
+                }
+
+                // 去掉黑场时各段为黑场之间的内容，否则在黑场中间切分
+                if *drop_black {
+                    let total_duration = self.get_video_duration(&request.video_path)?;
+                    let ranges = ranges_between(&blacks, total_duration);
+                    return self.build_segments(request, ranges, total_duration);
+                }
+                blacks.iter().map(|b| (b.start + b.end) / 2.0).collect()
+            }
+            SplitType::Subtitles {
+                source,
+                target_duration,
+            } => {
+                let cues = match source {
+                    SubtitleSource::File { path } => subtitles::load_file(path)?,
+                    SubtitleSource::Stream { index } => {
+                        subtitles::extract_stream(
+                            &self.ffmpeg_path,
+                            &request.video_path,
+                            *index,
+                            cancel,
+                        )
+                        .await?
+                    }
+                };
+                let total_duration = self.get_video_duration(&request.video_path)?;
+                let ranges = subtitle_ranges(&cues, *target_duration, total_duration);
+                return self.build_segments(request, ranges, total_duration);
+            }
+            SplitType::Jingle {
+                reference_path,
+                min_score,
+            } => {
+                let matches = self
+                    .processor
+                    .detect_jingles(
+                        &request.video_path.to_string_lossy(),
+                        reference_path,
+                        *min_score,
+                        Some(window),
+                        Some(cancel),
+                    )
+                    .await?;
+
+                // 在每次出现之前切分，片头音效留在下一段的开头
+                matches.into_iter().map(|m| m.time).collect()
+            }
+            SplitType::Ranges { ranges, .. } => {
+                let total_duration = self.get_video_duration(&request.video_path)?;
+                let kept = kept_ranges(ranges, total_duration);
+                if kept.is_empty() {
+                    return Err(VideoError::ProcessingError(
+                        "No content left to keep".to_string(),
+                    ));
+                }
+                return self.build_segments(request, kept, total_duration);
+            }
         };
 
-        if split_points.is_empty() {
+        // 按大小分割时整个视频可能本来就不超过上限
+        if split_points.is_empty() && !matches!(request.split_type, SplitType::Size { .. }) {
             return Err(VideoError::ProcessingError(
                 "No split points generated".to_string(),
             ));
         }
 
-        let output_files = self.process_splits(&request, &split_points, window).await?;
+        let total_duration = self.get_video_duration(&request.video_path)?;
+
+        // 请求的分割边界：0、各分割点、视频结尾
+        let mut requested = vec![0.0];
+        requested.extend(
+            split_points
+                .iter()
+                .copied()
+                .filter(|&t| t > 0.0 && t < total_duration),
+        );
+        requested.sort_by(|a, b| a.total_cmp(b));
+        requested.dedup();
+        requested.push(total_duration);
+
+        // 手动分割的标题属于从该分割点开始的段
+        let title_at = |start: f64| match &request.split_type {
+            SplitType::Manual {
+                split_points,
+                titles,
+            } => split_points
+                .iter()
+                .zip(titles)
+                .find(|(&t, _)| (t.max(0.0) - start).abs() < 1e-6)
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty()),
+            _ => None,
+        };
+
+        // 每个段从前一个分割点到当前分割点
+        let ranges = requested
+            .windows(2)
+            .map(|w| PlannedRange {
+                start: w[0],
+                end: w[1],
+                title: title_at(w[0]),
+            })
+            .collect();
+
+        self.build_segments(request, ranges, total_duration)
+    }
+
+    // 把请求的范围转换为分割段
+    fn build_segments(
+        &self,
+        request: &SplitRequest,
+        ranges: Vec<PlannedRange>,
+        total_duration: f64,
+    ) -> VideoResult<Vec<SegmentPlan>> {
+        let ranges = apply_constraints(ranges, &request.constraints, total_duration)?;
+
+        // 复制模式下只能在关键帧处切分，需要把分割点移到最近的关键帧
+        let keyframes = match request.split_mode {
+            SplitMode::Copy => self.get_keyframes(&request.video_path)?,
+            SplitMode::Reencode | SplitMode::Smart => Vec::new(),
+        };
+        let snap = |t: f64| {
+            if request.split_mode != SplitMode::Copy || t <= 0.0 || t >= total_duration {
+                t
+            } else {
+                snap_to_keyframe(t, &keyframes)
+            }
+        };
+
+        // 对齐后重合的分割点会产生空段，直接丢弃
+        let mut segments = Vec::new();
+        for range in ranges {
+            let (start, end) = (snap(range.start), snap(range.end));
+            if end > start {
+                segments.push(SegmentPlan {
+                    index: segments.len(),
+                    requested_start: range.start,
+                    requested_end: range.end,
+                    start,
+                    end,
+                    title: range.title,
+                });
+            }
+        }
+
+        Ok(segments)
+    }
+
+    // 返回选中章节的范围和视频总时长
+    async fn chapter_ranges(
+        &self,
+        video_path: &Path,
+        indices: Option<&[usize]>,
+    ) -> VideoResult<(Vec<PlannedRange>, f64)> {
+        let info = self
+            .processor
+            .get_video_info(&video_path.to_string_lossy())
+            .await?;
+
+        if info.chapters.is_empty() {
+            return Err(VideoError::ProcessingError(
+                "Video has no chapters".to_string(),
+            ));
+        }
+
+        let ranges: Vec<PlannedRange> = info
+            .chapters
+            .into_iter()
+            .filter(|c| indices.is_none_or(|indices| indices.contains(&c.index)))
+            .map(|c| PlannedRange {
+                start: c.start,
+                end: c.end,
+                title: c.title,
+            })
+            .collect();
+
+        if ranges.is_empty() {
+            return Err(VideoError::ProcessingError(
+                "None of the selected chapters exist".to_string(),
+            ));
+        }
+
+        Ok((ranges, info.duration))
+    }
+
+    // 执行分割计划，completed 中已完成的段会被跳过
+    pub async fn execute_plan(
+        &self,
+        request: &SplitRequest,
+        plan: &[SegmentPlan],
+        completed: Vec<SegmentResult>,
+        window: &WebviewWindow,
+        cancel: &CancelToken,
+        on_segment_done: &(dyn Fn(&SegmentResult) + Send + Sync),
+    ) -> VideoResult<SplitResult> {
+        let start_time = std::time::Instant::now();
+
+        let pending: Vec<&SegmentPlan> = plan
+            .iter()
+            .filter(|s| !completed.iter().any(|c| c.index == s.index))
+            .collect();
 
-        let processing_time = start_time.elapsed().as_secs_f64();
+        let keyframes = match (request.split_mode, &request.split_type) {
+            (SplitMode::Smart, _) | (SplitMode::Copy, SplitType::Size { .. }) => {
+                self.get_keyframes(&request.video_path)?
+            }
+            _ => Vec::new(),
+        };
 
-        Ok(SplitResult {
-            success: true,
+        // 智能渲染需要与源视频参数一致的编码器，无法匹配时整段重新编码
+        let smart_encoder = match request.split_mode {
+            SplitMode::Smart => {
+                let encoder = self.probe_smart_encoder(&request.video_path)?;
+                if encoder.is_none() {
+                    println!("Source codec cannot be matched, falling back to full re-encode");
+                }
+                encoder
+            }
+            _ => None,
+        };
+
+        let stream_types = match request.verify {
+            Some(_) => mapped_stream_types(
+                self.probe_streams(&request.video_path)?.1,
+                &request.output_format,
+            ),
+            None => Vec::new(),
+        };
+
+        // 并行数不超过段数，本任务分到的CPU线程在各个ffmpeg进程之间平分
+        // 按大小分割时每段的起点取决于上一段的实际结果，只能逐段处理
+        let cores = self.cores.load(Ordering::SeqCst);
+        let workers = match request.split_type {
+            SplitType::Size { .. } => 1,
+            _ => request
+                .workers
+                .unwrap_or(cores)
+                .clamp(1, pending.len().max(1)),
+        };
+        let run = SplitRun {
+            request,
+            source: SourceProfile {
+                keyframes,
+                smart_encoder,
+                threads: (cores / workers).max(1),
+                stream_types,
+            },
+            progress: SplitProgress::new(window, plan.iter().map(|s| s.end - s.start).collect()),
+            cancel,
+            on_segment_done,
+            halted: AtomicBool::new(false),
+        };
+        for segment in &completed {
+            run.progress.finish_segment(segment.index);
+        }
+
+        println!(
+            "Processing {} of {} segments with {} workers, {} threads each",
+            pending.len(),
+            plan.len(),
+            workers,
+            run.source.threads
+        );
+
+        let mut segments = match request.split_type {
+            SplitType::Size { max_bytes } => {
+                self.process_size_segments(&run, plan, completed, max_bytes)
+                    .await
+            }
+            _ => {
+                // buffered保证结果按段的顺序返回
+                // 按下标迭代，避免闭包参数带引用导致任务无法跨线程调度
+                let run = &run;
+                let pending = &pending;
+                let mut stream = stream::iter((0..pending.len()).map(|i| async move {
+                    let result = self.process_segment_with_retries(run, pending[i]).await;
+                    if result.status == SegmentStatus::Completed {
+                        run.progress.finish_segment(result.index);
+                        (run.on_segment_done)(&result);
+                    }
+                    result
+                }))
+                .buffered(workers);
+
+                let mut segments = completed;
+                while let Some(result) = stream.next().await {
+                    segments.push(result);
+                }
+                segments
+            }
+        };
+        segments.sort_by_key(|s| s.index);
+
+        let mut result = Self::summarize(segments, cancel.is_cancelled(), start_time);
+        if let SplitType::Ranges { join: true, .. } = request.split_type {
+            if result.success {
+                self.join_outputs(request, &mut result, cancel).await;
+            }
+        }
+
+        Ok(result)
+    }
+
+    // 把各段拼接成一个文件，成功后删除各段；失败时保留各段并记录错误
+    async fn join_outputs(
+        &self,
+        request: &SplitRequest,
+        result: &mut SplitResult,
+        cancel: &CancelToken,
+    ) {
+        let target = match resolve_output(request, joined_output_path(request)) {
+            Ok(OutputTarget::Write(path)) => path,
+            Ok(OutputTarget::Existing(path)) => {
+                println!("Joined output already exists, skipping: {}", path.display());
+                for piece in &result.output_files {
+                    let _ = std::fs::remove_file(piece);
+                }
+                result.output_files = vec![path];
+                return;
+            }
+            Err(e) => {
+                result.success = false;
+                result.errors.push(e.to_string());
+                return;
+            }
+        };
+
+        let part_file = part_path(&target);
+        let output_args = [
+            "-f".to_string(),
+            muxer_name(&request.output_format).to_string(),
+        ];
+        let joined = self
+            .concat_files(
+                cancel,
+                &result.output_files,
+                &target.with_extension("join.txt"),
+                &output_args,
+                &part_file,
+            )
+            .await
+            .and_then(|_| commit_output(&part_file, &target, request.overwrite_policy));
+
+        match joined {
+            Ok(()) => {
+                println!(
+                    "Joined {} segments into {}",
+                    result.output_files.len(),
+                    target.display()
+                );
+                for piece in &result.output_files {
+                    let _ = std::fs::remove_file(piece);
+                }
+                result.output_files = vec![target];
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&part_file);
+                result.success = false;
+                match e {
+                    VideoError::Cancelled => result.cancelled = true,
+                    e => result
+                        .errors
+                        .push(format!("Failed to join segments: {}", e)),
+                }
+            }
+        }
+    }
+
+    // 只有全部分段都完成时才算成功，失败的分段记录在 errors 中
+    fn summarize(
+        segments: Vec<SegmentResult>,
+        cancelled: bool,
+        start_time: std::time::Instant,
+    ) -> SplitResult {
+        let output_files = segments
+            .iter()
+            .filter(|s| s.status == SegmentStatus::Completed)
+            .map(|s| s.output_file.clone())
+            .collect();
+        let errors: Vec<String> = segments
+            .iter()
+            .filter(|s| s.status == SegmentStatus::Failed)
+            .map(|s| {
+                format!(
+                    "Segment {} failed after {} attempt(s): {}",
+                    s.index + 1,
+                    s.attempts,
+                    s.error.as_deref().unwrap_or("unknown error")
+                )
+            })
+            .collect();
+        let success = !cancelled
+            && segments
+                .iter()
+                .all(|s| s.status == SegmentStatus::Completed);
+
+        SplitResult {
+            success,
+            cancelled,
             output_files,
-            errors: Vec::new(),
-            processing_time,
+            errors,
+            processing_time: start_time.elapsed().as_secs_f64(),
+            segments,
+        }
+    }
+
+    // 逐段处理，输出超过上限时缩短该段，剩余部分并入下一段
+    // 计划中的分割点只是按码率估计的初始值，后续段按已输出段的实际码率重新估计
+    async fn process_size_segments(
+        &self,
+        run: &SplitRun<'_>,
+        plan: &[SegmentPlan],
+        completed: Vec<SegmentResult>,
+        max_bytes: u64,
+    ) -> Vec<SegmentResult> {
+        const MAX_ADJUSTMENTS: u32 = 5;
+        const EPSILON: f64 = 0.001;
+
+        let total_end = plan.last().map(|s| s.end).unwrap_or(0.0);
+        // 继续执行时从已完成部分的结尾开始
+        let mut start = completed.iter().map(|s| s.end).fold(0.0, f64::max);
+        let mut index = completed.iter().map(|s| s.index + 1).max().unwrap_or(0);
+        let mut estimate = plan
+            .get(index)
+            .or(plan.last())
+            .map(|s| s.end - s.start)
+            .unwrap_or(total_end);
+        let mut segments = completed;
+
+        while total_end - start > EPSILON
+            && !run.cancel.is_cancelled()
+            && !run.halted.load(Ordering::SeqCst)
+        {
+            let mut end = self.size_cut_point(run, start, start + estimate, total_end);
+            let mut adjustments = 0;
+            let result = loop {
+                run.progress.set_segment_duration(index, end - start);
+                let segment = SegmentPlan {
+                    index,
+                    requested_start: start,
+                    requested_end: end,
+                    start,
+                    end,
+                    title: None,
+                };
+                let mut result = self.process_segment_with_retries(run, &segment).await;
+                // attempts 为0表示按覆盖策略保留的已有文件，不能删除
+                if result.status != SegmentStatus::Completed || result.attempts == 0 {
+                    break result;
+                }
+
+                let size = std::fs::metadata(&result.output_file)
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                if size > 0 {
+                    estimate = (end - start) * max_bytes as f64 / size as f64 * SIZE_MARGIN;
+                }
+                if size <= max_bytes {
+                    break result;
+                }
+
+                let shorter = self.size_cut_point(run, start, start + estimate, total_end);
+                if adjustments >= MAX_ADJUSTMENTS || shorter >= end {
+                    // 超出上限的文件不能以正式文件名留在输出目录中
+                    if let Err(e) = std::fs::remove_file(&result.output_file) {
+                        println!(
+                            "Failed to remove oversized output {:?}: {}",
+                            result.output_file, e
+                        );
+                    }
+                    result.status = SegmentStatus::Failed;
+                    result.error = Some(format!(
+                        "Segment is {} bytes, exceeding the {} byte limit, and cannot be cut shorter",
+                        size, max_bytes
+                    ));
+                    if run.request.failure_policy == FailurePolicy::Stop {
+                        run.halted.store(true, Ordering::SeqCst);
+                    }
+                    break result;
+                }
+
+                println!(
+                    "Segment {} is {} bytes, exceeding {} bytes; shortening to {:.2}s",
+                    index + 1,
+                    size,
+                    max_bytes,
+                    shorter - start
+                );
+                let _ = std::fs::remove_file(&result.output_file);
+                end = shorter;
+                adjustments += 1;
+            };
+
+            match result.status {
+                SegmentStatus::Completed => {
+                    run.progress.finish_segment(index);
+                    (run.on_segment_done)(&result);
+                }
+                SegmentStatus::Cancelled | SegmentStatus::Skipped => {
+                    segments.push(result);
+                    break;
+                }
+                SegmentStatus::Failed => {}
+            }
+            segments.push(result);
+            start = end;
+            index += 1;
+        }
+
+        segments
+    }
+
+    // 复制模式下取不超过 target 的最后一个关键帧，保证段不为空
+    // 最大长度限制同样生效，最小长度和前后预留会与大小上限冲突，不适用于按大小分割
+    fn size_cut_point(&self, run: &SplitRun<'_>, start: f64, target: f64, total_end: f64) -> f64 {
+        let target = match run.request.constraints.max_length {
+            Some(max_length) => target.min(start + max_length),
+            None => target,
+        };
+        if target >= total_end {
+            return total_end;
+        }
+        match run.request.split_mode {
+            SplitMode::Copy => run
+                .source
+                .keyframes
+                .iter()
+                .copied()
+                .rev()
+                .find(|&k| k > start && k <= target)
+                .or_else(|| run.source.keyframes.iter().copied().find(|&k| k > start))
+                .unwrap_or(total_end)
+                .min(total_end),
+            SplitMode::Reencode | SplitMode::Smart => target,
+        }
+    }
+
+    // 按平均码率估计不超过 max_bytes 的分割点
+    async fn generate_size_splits(
+        &self,
+        video_path: &Path,
+        max_bytes: u64,
+    ) -> VideoResult<Vec<f64>> {
+        if max_bytes == 0 {
+            return Err(VideoError::ProcessingError(
+                "Maximum segment size must be greater than zero".to_string(),
+            ));
+        }
+
+        let info = self
+            .processor
+            .get_video_info(&video_path.to_string_lossy())
+            .await?;
+
+        // 容器没有记录码率时用文件大小推算
+        let bitrate = if info.bitrate > 0 {
+            info.bitrate as f64
+        } else if info.duration > 0.0 {
+            info.size as f64 * 8.0 / info.duration
+        } else {
+            return Err(VideoError::InvalidFormat(
+                "Unable to determine video bitrate".to_string(),
+            ));
+        };
+
+        let segment_duration = max_bytes as f64 * 8.0 / bitrate * SIZE_MARGIN;
+        let mut split_points = Vec::new();
+        let mut current_time = segment_duration;
+        while current_time < info.duration {
+            split_points.push(current_time);
+            current_time += segment_duration;
+        }
+
+        Ok(split_points)
+    }
+
+    // 按失败策略处理单个分段，失败不会中断其他分段
+    async fn process_segment_with_retries(
+        &self,
+        run: &SplitRun<'_>,
+        segment: &SegmentPlan,
+    ) -> SegmentResult {
+        let max_attempts = match run.request.failure_policy {
+            FailurePolicy::Stop => 1,
+            FailurePolicy::Continue { retries, .. } => retries + 1,
+        };
+        let target = resolve_output(run.request, output_path(run.request, segment));
+        let output_file = match &target {
+            Ok(OutputTarget::Write(path) | OutputTarget::Existing(path)) => path.clone(),
+            Err(_) => output_path(run.request, segment),
+        };
+        let result = |status, attempts, error, verification| SegmentResult {
+            index: segment.index,
+            requested_start: segment.requested_start,
+            requested_end: segment.requested_end,
+            start: segment.start,
+            end: segment.end,
+            title: segment.title.clone(),
+            output_file: output_file.clone(),
+            status,
+            attempts,
+            error,
+            verification,
+        };
+
+        match target {
+            Ok(OutputTarget::Write(_)) => {}
+            Ok(OutputTarget::Existing(path)) => {
+                println!(
+                    "Segment {} already exists, skipping: {}",
+                    segment.index + 1,
+                    path.display()
+                );
+                return result(SegmentStatus::Completed, 0, None, None);
+            }
+            Err(e) => {
+                if run.request.failure_policy == FailurePolicy::Stop {
+                    run.halted.store(true, Ordering::SeqCst);
+                }
+                return result(SegmentStatus::Failed, 0, Some(e.to_string()), None);
+            }
+        }
+
+        // 先写入临时文件，成功后再改名，中断时不会留下看似完整的输出
+        let part_file = part_path(&output_file);
+        let mut attempts = 0;
+        let mut last_error = None;
+        let mut last_verification = None;
+        while attempts < max_attempts {
+            if run.cancel.is_cancelled() {
+                return result(SegmentStatus::Cancelled, attempts, None, None);
+            }
+            if run.halted.load(Ordering::SeqCst) {
+                return result(SegmentStatus::Skipped, attempts, None, None);
+            }
+
+            if attempts > 0 {
+                if let FailurePolicy::Continue { backoff_ms, .. } = run.request.failure_policy {
+                    let delay = backoff_ms.saturating_mul(1 << (attempts - 1).min(16));
+                    println!(
+                        "Retrying segment {} in {} ms (attempt {} of {})",
+                        segment.index + 1,
+                        delay,
+                        attempts + 1,
+                        max_attempts
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_millis(delay)) => {}
+                        _ = run.cancel.cancelled() => {
+                            return result(SegmentStatus::Cancelled, attempts, None, None);
+                        }
+                    }
+                }
+            }
+
+            attempts += 1;
+            let verified = match self.process_segment(run, segment, &part_file).await {
+                Ok(()) => match &run.request.verify {
+                    Some(options) => self
+                        .verify_segment(run, segment, &part_file, options)
+                        .await
+                        .map(Some),
+                    None => Ok(None),
+                },
+                Err(e) => Err(e),
+            };
+
+            match verified {
+                // 校验失败的分段与ffmpeg失败一样按策略重试
+                Ok(Some(report)) if !report.passed => {
+                    let _ = std::fs::remove_file(&part_file);
+                    println!(
+                        "Segment {} attempt {} failed verification: {:?}",
+                        segment.index + 1,
+                        attempts,
+                        report.issues
+                    );
+                    last_error = Some(format!("Verification failed: {}", report.issues.join("; ")));
+                    last_verification = Some(report);
+                }
+                Ok(report) => {
+                    match commit_output(&part_file, &output_file, run.request.overwrite_policy) {
+                        Ok(()) => return result(SegmentStatus::Completed, attempts, None, report),
+                        Err(e) => {
+                            let _ = std::fs::remove_file(&part_file);
+                            println!(
+                                "Segment {} attempt {} failed: {}",
+                                segment.index + 1,
+                                attempts,
+                                e
+                            );
+                            last_error = Some(e.to_string());
+                        }
+                    }
+                }
+                Err(VideoError::Cancelled) => {
+                    return result(SegmentStatus::Cancelled, attempts, None, None)
+                }
+                Err(e) => {
+                    println!(
+                        "Segment {} attempt {} failed: {}",
+                        segment.index + 1,
+                        attempts,
+                        e
+                    );
+                    last_error = Some(stderr_excerpt(&e.to_string()));
+                }
+            }
+        }
+
+        if run.request.failure_policy == FailurePolicy::Stop {
+            run.halted.store(true, Ordering::SeqCst);
+        }
+        result(
+            SegmentStatus::Failed,
+            attempts,
+            last_error,
+            last_verification,
+        )
+    }
+
+    // 检查输出的时长、流和（可选）解码是否正常
+    async fn verify_segment(
+        &self,
+        run: &SplitRun<'_>,
+        segment: &SegmentPlan,
+        output_file: &Path,
+        options: &VerifyOptions,
+    ) -> VideoResult<VerificationReport> {
+        let expected_duration = segment.end - segment.start;
+        let mut issues = Vec::new();
+
+        let (duration, streams) = match self.probe_streams(output_file) {
+            Ok(probe) => probe,
+            Err(e) => {
+                issues.push(format!("Unable to probe output: {}", e));
+                (None, Vec::new())
+            }
+        };
+
+        match duration {
+            Some(duration) if (duration - expected_duration).abs() > options.duration_tolerance => {
+                issues.push(format!(
+                    "Duration {:.3}s differs from planned {:.3}s",
+                    duration, expected_duration
+                ));
+            }
+            Some(_) => {}
+            None => issues.push("Output duration is unknown".to_string()),
+        }
+
+        let missing_streams: Vec<String> = run
+            .source
+            .stream_types
+            .iter()
+            .filter(|t| !streams.contains(t))
+            .cloned()
+            .collect();
+        if !missing_streams.is_empty() {
+            issues.push(format!("Missing streams: {}", missing_streams.join(", ")));
+        }
+
+        let decode_errors = if options.decode_check {
+            let args = [
+                "-v".to_string(),
+                "error".to_string(),
+                "-i".to_string(),
+                output_file.to_string_lossy().to_string(),
+                "-f".to_string(),
+                "null".to_string(),
+                "-".to_string(),
+            ];
+            let output =
+                run_with_progress(&self.ffmpeg_path, &args, Some(run.cancel), |_| {}).await?;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !output.success || !stderr.trim().is_empty() {
+                issues.push("Decode pass reported errors".to_string());
+                Some(stderr_excerpt(&stderr))
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        Ok(VerificationReport {
+            passed: issues.is_empty(),
+            expected_duration,
+            duration,
+            streams,
+            missing_streams,
+            decode_errors,
+            issues,
         })
     }
 
+    // 返回容器时长和去重后的流类型
+    fn probe_streams(&self, path: &Path) -> VideoResult<(Option<f64>, Vec<String>)> {
+        let output = Command::new(&self.ffprobe_path)
+            .args([
+                "-v",
+                "error",
+                "-show_entries",
+                "format=duration:stream=codec_type",
+                "-of",
+                "json",
+                path.to_str().unwrap(),
+            ])
+            .output()
+            .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffprobe: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(VideoError::FFmpegError(format!(
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+            VideoError::FFmpegError(format!("Failed to parse ffprobe output: {}", e))
+        })?;
+
+        let duration = json
+            .get("format")
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .and_then(|s| s.parse().ok());
+
+        let mut streams: Vec<String> = Vec::new();
+        for stream in json
+            .get("streams")
+            .and_then(|s| s.as_array())
+            .into_iter()
+            .flatten()
+        {
+            if let Some(codec_type) = stream.get("codec_type").and_then(|t| t.as_str()) {
+                if !streams.iter().any(|s| s == codec_type) {
+                    streams.push(codec_type.to_string());
+                }
+            }
+        }
+
+        Ok((duration, streams))
+    }
+
     fn generate_time_splits(
         &self,
         video_path: &Path,
@@ -142,8 +1101,7 @@ impl VideoSplitter {
     }
 
     fn get_video_duration(&self, video_path: &Path) -> VideoResult<f64> {
-        let ffprobe_path = Self::find_ffprobe_path();
-        let output = Command::new(ffprobe_path)
+        let output = Command::new(&self.ffprobe_path)
             .args([
                 "-v",
                 "quiet",
@@ -169,156 +1127,959 @@ impl VideoSplitter {
             .map_err(|_| VideoError::FFmpegError("Invalid duration format".to_string()))
     }
 
-    async fn process_splits(
-        &self,
-        request: &SplitRequest,
-        split_points: &[f64],
-        window: &WebviewWindow,
-    ) -> VideoResult<Vec<PathBuf>> {
-        let mut output_files = Vec::new();
+    // 检查之前完成的段是否仍然存在且可以读取
+    pub fn is_output_intact(&self, segment: &SegmentResult) -> bool {
+        const TOLERANCE: f64 = 1.0;
 
-        // 创建分割段：每个段从前一个分割点到当前分割点
-        let mut segments = Vec::new();
-        let mut start_time = 0.0;
+        let size = std::fs::metadata(&segment.output_file)
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if size == 0 {
+            return false;
+        }
 
-        for &end_time in split_points {
-            segments.push((start_time, end_time));
-            start_time = end_time;
+        match self.get_video_duration(&segment.output_file) {
+            Ok(duration) => (duration - (segment.end - segment.start)).abs() <= TOLERANCE,
+            Err(_) => false,
         }
+    }
 
-        // 添加最后一个段（从最后一个分割点到视频结束）
-        let total_duration = self.get_video_duration(&request.video_path)?;
-        if start_time < total_duration {
-            segments.push((start_time, total_duration));
+    fn get_keyframes(&self, video_path: &Path) -> VideoResult<Vec<f64>> {
+        // 只读取数据包而不解码，flags中带K的是关键帧
+        let output = Command::new(&self.ffprobe_path)
+            .args([
+                "-v",
+                "error",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "packet=pts_time,flags",
+                "-of",
+                "csv=p=0",
+                video_path.to_str().unwrap(),
+            ])
+            .output()
+            .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffprobe: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(VideoError::FFmpegError(format!(
+                "Failed to read keyframes: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
         }
 
-        // 处理每个段
-        for (i, (start, end)) in segments.iter().enumerate() {
-            let output_file = self
-                .process_segment(request, *start, *end, i, segments.len(), window)
-                .await?;
-            output_files.push(output_file);
+        let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.trim().split(',');
+                let time = parts.next()?.parse::<f64>().ok()?;
+                let flags = parts.next()?;
+                flags.contains('K').then_some(time)
+            })
+            .collect();
+        keyframes.sort_by(|a, b| a.total_cmp(b));
+
+        if keyframes.is_empty() {
+            return Err(VideoError::ProcessingError(
+                "No keyframes found in video stream".to_string(),
+            ));
         }
 
-        Ok(output_files)
+        Ok(keyframes)
     }
 
+    // 把分段写到 output_path，调用方负责改名为最终文件
     async fn process_segment(
         &self,
-        request: &SplitRequest,
-        start_time: f64,
-        end_time: f64,
-        segment_index: usize,
-        total_segments: usize,
-        _window: &WebviewWindow,
-    ) -> VideoResult<PathBuf> {
-        let output_filename = format!(
-            "segment_{}_{:03}.{}",
-            request
-                .video_path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("segment"),
-            segment_index + 1,
-            request.output_format
-        );
-
-        let output_path = request.output_dir.join(output_filename);
+        run: &SplitRun<'_>,
+        segment: &SegmentPlan,
+        output_path: &Path,
+    ) -> VideoResult<()> {
+        let request = run.request;
+        let source = &run.source;
+        let progress = &run.progress;
+        let segment_index = segment.index;
+        let start_time = segment.start;
+        let end_time = segment.end;
+        // 临时文件的扩展名无法推断格式，需要显式指定
+        let mut output_args = vec![
+            "-f".to_string(),
+            muxer_name(&request.output_format).to_string(),
+        ];
+        if let Some(title) = &segment.title {
+            output_args.extend(["-metadata".to_string(), format!("title={}", title)]);
+        }
 
         println!(
             "Processing segment {} of {}: {:.2}s - {:.2}s",
             segment_index + 1,
-            total_segments,
+            progress.total_segments(),
             start_time,
             end_time
         );
 
         let duration = end_time - start_time;
+        let with_threads = |mut args: Vec<String>| {
+            args.extend(["-threads".to_string(), source.threads.to_string()]);
+            args
+        };
+        let with_output_args = |mut args: Vec<String>| {
+            args.extend(output_args.iter().cloned());
+            args
+        };
+        let on_progress = |p: &FfmpegProgress| progress.update(segment_index, p.out_time, p.speed);
+        let result = match (request.split_mode, &source.smart_encoder) {
+            // 分割点已对齐到关键帧，直接复制流
+            (SplitMode::Copy, _) => {
+                self.run_ffmpeg(
+                    run.cancel,
+                    &cut_args(
+                        &request.video_path,
+                        start_time,
+                        duration,
+                        &with_output_args(copy_args()),
+                        output_path,
+                    ),
+                    on_progress,
+                )
+                .await
+            }
+            (SplitMode::Smart, Some(encoder)) => {
+                self.smart_render_segment(
+                    run,
+                    segment,
+                    &with_threads(encoder.clone()),
+                    &output_args,
+                    output_path,
+                )
+                .await
+            }
+            _ => {
+                self.run_ffmpeg(
+                    run.cancel,
+                    &cut_args(
+                        &request.video_path,
+                        start_time,
+                        duration,
+                        &with_output_args(with_threads(reencode_args())),
+                        output_path,
+                    ),
+                    on_progress,
+                )
+                .await
+            }
+        };
+
+        // 失败或取消时删除写了一半的输出文件
+        if result.is_err() {
+            let _ = std::fs::remove_file(output_path);
+        }
+
+        result.map_err(|e| match e {
+            VideoError::FFmpegError(stderr) => VideoError::FFmpegError(format!(
+                "ffmpeg failed for segment {}: {}",
+                segment_index + 1,
+                stderr
+            )),
+            other => other,
+        })
+    }
+
+    async fn smart_render_segment(
+        &self,
+        run: &SplitRun<'_>,
+        segment: &SegmentPlan,
+        encoder: &[String],
+        output_args: &[String],
+        output_path: &Path,
+    ) -> VideoResult<()> {
+        const EPSILON: f64 = 0.001;
+
+        let video_path = &run.request.video_path;
+        let keyframes = &run.source.keyframes;
+        let progress = &run.progress;
+        let start_time = segment.start;
+        let end_time = segment.end;
+        // 各部分的进度需要加上该部分在段内的偏移
+        let on_progress = |offset: f64| {
+            move |p: &FfmpegProgress| progress.update(segment.index, offset + p.out_time, p.speed)
+        };
+
+        let first_key = keyframes
+            .iter()
+            .copied()
+            .find(|&k| k >= start_time - EPSILON);
+        let last_key = keyframes
+            .iter()
+            .copied()
+            .rev()
+            .find(|&k| k <= end_time + EPSILON);
+        let (head_key, tail_key) = match (first_key, last_key) {
+            (Some(head), Some(tail)) if tail > head + EPSILON => (head, tail),
+            // 段内没有完整的GOP，整段用匹配的参数重新编码
+            _ => {
+                return self
+                    .run_ffmpeg(
+                        run.cancel,
+                        &cut_args(
+                            video_path,
+                            start_time,
+                            end_time - start_time,
+                            &[encoder, output_args].concat(),
+                            output_path,
+                        ),
+                        on_progress(0.0),
+                    )
+                    .await
+            }
+        };
+
+        // 头部和尾部不完整的GOP重新编码，中间整段复制
+        let mut ranges = Vec::new();
+        if head_key - start_time > EPSILON {
+            ranges.push((start_time, head_key, encoder.to_vec()));
+        }
+        ranges.push((head_key, tail_key, copy_args()));
+        if end_time - tail_key > EPSILON {
+            ranges.push((tail_key, end_time, encoder.to_vec()));
+        }
+
+        let mut pieces = Vec::new();
+
+        let result = async {
+            for (i, (start, end, codec)) in ranges.iter().enumerate() {
+                let piece_path = output_path.with_extension(format!("smart{}.ts", i));
+                pieces.push(piece_path.clone());
+                self.run_ffmpeg(
+                    run.cancel,
+                    &cut_args(video_path, *start, end - start, codec, &piece_path),
+                    on_progress(start - start_time),
+                )
+                .await?;
+            }
+
+            self.concat_files(
+                run.cancel,
+                &pieces,
+                &output_path.with_extension("smart.txt"),
+                output_args,
+                output_path,
+            )
+            .await
+        }
+        .await;
+
+        // 清理中间文件
+        for piece in &pieces {
+            let _ = std::fs::remove_file(piece);
+        }
+
+        result
+    }
+
+    // 用concat分离器无损拼接多个文件，list_path 为临时的文件列表
+    async fn concat_files(
+        &self,
+        cancel: &CancelToken,
+        inputs: &[PathBuf],
+        list_path: &Path,
+        output_args: &[String],
+        output_path: &Path,
+    ) -> VideoResult<()> {
+        let list: String = inputs
+            .iter()
+            .map(|p| format!("file '{}'\n", p.to_string_lossy().replace('\'', "'\\''")))
+            .collect();
+        std::fs::write(list_path, list)?;
+
+        let mut args = ["-y", "-f", "concat", "-safe", "0", "-i"]
+            .map(String::from)
+            .to_vec();
+        args.push(list_path.to_string_lossy().to_string());
+        args.extend(["-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from));
+        args.extend(output_args.iter().cloned());
+        args.push(output_path.to_string_lossy().to_string());
 
-        let output = Command::new("ffmpeg")
+        let result = self.run_ffmpeg(cancel, &args, |_| {}).await;
+        let _ = std::fs::remove_file(list_path);
+        result
+    }
+
+    fn probe_smart_encoder(&self, video_path: &Path) -> VideoResult<Option<Vec<String>>> {
+        let output = Command::new(&self.ffprobe_path)
             .args([
-                "-y", // 覆盖输出文件
-                "-ss",
-                &start_time.to_string(), // 先设置开始时间
-                "-i",
-                request.video_path.to_str().unwrap(),
-                "-t",
-                &duration.to_string(), // 使用持续时间
-                "-c:v", "libx264", // 重新编码视频以确保精确分割
-                "-c:a", "aac", // 重新编码音频
-                "-preset", "fast", // 使用快速预设
-                "-crf", "23", // 质量设置
-                "-avoid_negative_ts",
-                "make_zero",
-                output_path.to_str().unwrap(),
+                "-v",
+                "error",
+                "-show_entries",
+                "stream=codec_type,codec_name,profile,pix_fmt,level,sample_rate,channels",
+                "-of",
+                "json",
+                video_path.to_str().unwrap(),
             ])
             .output()
-            .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+            .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffprobe: {}", e)))?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
             return Err(VideoError::FFmpegError(format!(
-                "ffmpeg failed for segment {}: {}",
-                segment_index + 1,
-                stderr
+                "ffprobe failed: {}",
+                String::from_utf8_lossy(&output.stderr)
             )));
         }
 
-        Ok(output_path)
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| {
+            VideoError::FFmpegError(format!("Failed to parse ffprobe output: {}", e))
+        })?;
+
+        let streams = json
+            .get("streams")
+            .and_then(|s| s.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let find_stream = |codec_type: &str| {
+            streams
+                .iter()
+                .find(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some(codec_type))
+        };
+        let field = |stream: &serde_json::Value, key: &str| {
+            stream
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+
+        let video = match find_stream("video") {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+
+        let mut args: Vec<String> = Vec::new();
+        let profile = field(video, "profile");
+        match field(video, "codec_name").as_str() {
+            "h264" => {
+                let profile = match profile.as_str() {
+                    "Baseline" | "Constrained Baseline" => "baseline",
+                    "Main" => "main",
+                    "High" => "high",
+                    "High 10" => "high10",
+                    "High 4:2:2" => "high422",
+                    "High 4:4:4 Predictive" => "high444",
+                    _ => return Ok(None),
+                };
+                args.extend(["-c:v", "libx264", "-profile:v", profile].map(String::from));
+                if let Some(level) = video
+                    .get("level")
+                    .and_then(|l| l.as_i64())
+                    .filter(|&l| l > 0)
+                {
+                    args.extend([
+                        "-level:v".to_string(),
+                        format!("{}.{}", level / 10, level % 10),
+                    ]);
+                }
+            }
+            "hevc" => {
+                let profile = match profile.as_str() {
+                    "Main" => "main",
+                    "Main 10" => "main10",
+                    _ => return Ok(None),
+                };
+                args.extend(["-c:v", "libx265", "-profile:v", profile].map(String::from));
+            }
+            _ => return Ok(None),
+        }
+        let pix_fmt = field(video, "pix_fmt");
+        if !pix_fmt.is_empty() {
+            args.extend(["-pix_fmt".to_string(), pix_fmt]);
+        }
+        // 重新编码的GOP使用较高质量，尽量与复制部分保持一致
+        args.extend(["-preset", "fast", "-crf", "18"].map(String::from));
+
+        match find_stream("audio") {
+            None => args.push("-an".to_string()),
+            Some(audio) if field(audio, "codec_name") == "aac" => {
+                args.extend(["-c:a", "aac"].map(String::from));
+                let sample_rate = field(audio, "sample_rate");
+                if !sample_rate.is_empty() {
+                    args.extend(["-ar".to_string(), sample_rate]);
+                }
+                if let Some(channels) = audio.get("channels").and_then(|c| c.as_u64()) {
+                    args.extend(["-ac".to_string(), channels.to_string()]);
+                }
+            }
+            // 复制的音频无法与重新编码的部分拼接
+            Some(_) => return Ok(None),
+        }
+
+        Ok(Some(args))
     }
 
-    pub async fn split_by_scenes(
+    async fn run_ffmpeg<F>(
         &self,
-        video_path: &Path,
-        scenes: &[ScenePoint],
-        output_dir: &Path,
-        output_format: &str,
-    ) -> VideoResult<Vec<PathBuf>> {
-        let mut output_files = Vec::new();
-
-        for (i, scene) in scenes.iter().enumerate() {
-            let start_time = if i == 0 { 0.0 } else { scenes[i - 1].time };
-            let end_time = scene.time;
-
-            let output_filename = format!(
-                "scene_{}_{:03}.{}",
-                video_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("scene"),
-                i + 1,
-                output_format
-            );
-
-            let output_path = output_dir.join(output_filename);
-
-            let output = Command::new(&self.ffmpeg_path)
-                .args([
-                    "-i",
-                    video_path.to_str().unwrap(),
-                    "-ss",
-                    &start_time.to_string(),
-                    "-to",
-                    &end_time.to_string(),
-                    "-c",
-                    "copy",
-                    "-avoid_negative_ts",
-                    "make_zero",
-                    output_path.to_str().unwrap(),
-                ])
-                .output()
-                .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+        cancel: &CancelToken,
+        args: &[String],
+        on_progress: F,
+    ) -> VideoResult<()>
+    where
+        F: FnMut(&FfmpegProgress),
+    {
+        let output = run_with_progress(&self.ffmpeg_path, args, Some(cancel), on_progress).await?;
+
+        if !output.success {
+            return Err(VideoError::FFmpegError(
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ));
+        }
 
-            if !output.status.success() {
-                return Err(VideoError::FFmpegError(format!(
-                    "ffmpeg failed: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                )));
+        Ok(())
+    }
+}
+
+// 合并连续的字幕直到达到目标时长，只在字幕之间的空隙中间切分
+// 重叠的字幕之间没有空隙，总是合并到同一段，段标题为其中的字幕文本
+fn subtitle_ranges(
+    cues: &[SubtitleCue],
+    target_duration: f64,
+    total_duration: f64,
+) -> Vec<PlannedRange> {
+    let mut groups: Vec<(f64, f64, Vec<&str>)> = Vec::new();
+    for cue in cues {
+        match groups.last_mut() {
+            Some((start, end, texts)) if cue.start <= *end || *end - *start < target_duration => {
+                *end = end.max(cue.end);
+                texts.push(&cue.text);
             }
+            _ => groups.push((cue.start, cue.end, vec![&cue.text])),
+        }
+    }
 
-            output_files.push(output_path);
+    (0..groups.len())
+        .map(|i| {
+            let (start, end, texts) = &groups[i];
+            PlannedRange {
+                start: match i {
+                    0 => 0.0,
+                    _ => (groups[i - 1].1 + start) / 2.0,
+                },
+                end: match groups.get(i + 1) {
+                    Some(next) => (end + next.0) / 2.0,
+                    None => total_duration,
+                },
+                title: Some(texts.join(" ")).filter(|t| !t.trim().is_empty()),
+            }
+        })
+        .collect()
+}
+
+// 依次合并过短的段、拆分过长的段，最后加上前后预留
+// 只合并首尾相接的段，避免把不连续的范围之间的内容带进输出
+fn apply_constraints(
+    ranges: Vec<PlannedRange>,
+    constraints: &SegmentConstraints,
+    total_duration: f64,
+) -> VideoResult<Vec<PlannedRange>> {
+    const EPSILON: f64 = 0.001;
+
+    if constraints.pre_roll < 0.0 || constraints.post_roll < 0.0 {
+        return Err(VideoError::ProcessingError(
+            "Pre-roll and post-roll must not be negative".to_string(),
+        ));
+    }
+    if let (Some(min), Some(max)) = (constraints.min_length, constraints.max_length) {
+        if min > max {
+            return Err(VideoError::ProcessingError(
+                "Minimum segment length exceeds maximum segment length".to_string(),
+            ));
         }
+    }
+    if constraints.max_length.is_some_and(|max| max <= 0.0) {
+        return Err(VideoError::ProcessingError(
+            "Maximum segment length must be greater than zero".to_string(),
+        ));
+    }
+
+    let mut ranges = ranges;
+    if let Some(min_length) = constraints.min_length {
+        let mut merged: Vec<PlannedRange> = Vec::new();
+        let merge = |last: &mut PlannedRange, range: PlannedRange| {
+            last.end = range.end;
+            if last.title.is_none() {
+                last.title = range.title;
+            }
+        };
+        for range in ranges {
+            match merged.last_mut() {
+                // 上一段还不够长时把当前段并入，够长之后从当前段开始新的一段
+                Some(last)
+                    if (range.start - last.end).abs() < EPSILON
+                        && last.end - last.start < min_length =>
+                {
+                    merge(last, range)
+                }
+                _ => merged.push(range),
+            }
+        }
+        // 最后一段过短时并入前一段
+        if merged.len() >= 2 {
+            let tail = &merged[merged.len() - 1];
+            let previous = &merged[merged.len() - 2];
+            if tail.end - tail.start < min_length && (tail.start - previous.end).abs() < EPSILON {
+                let tail = merged.pop().unwrap();
+                if let Some(last) = merged.last_mut() {
+                    merge(last, tail);
+                }
+            }
+        }
+        ranges = merged;
+    }
+
+    if let Some(max_length) = constraints.max_length {
+        ranges = ranges
+            .into_iter()
+            .flat_map(|range| {
+                let length = range.end - range.start;
+                let parts = (length / max_length - EPSILON).ceil().max(1.0) as usize;
+                let part_length = length / parts as f64;
+                (0..parts)
+                    .map(|i| PlannedRange {
+                        start: range.start + part_length * i as f64,
+                        end: match i + 1 {
+                            n if n == parts => range.end,
+                            n => range.start + part_length * n as f64,
+                        },
+                        title: range.title.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    Ok(ranges
+        .into_iter()
+        .map(|range| PlannedRange {
+            start: (range.start - constraints.pre_roll).max(0.0),
+            end: (range.end + constraints.post_roll).min(total_duration),
+            title: range.title,
+        })
+        .collect())
+}
+
+// 计算保留的范围：有 keep 范围时取其并集，否则为整个视频，再去掉所有 discard 范围
+fn kept_ranges(selections: &[RangeSelection], total_duration: f64) -> Vec<PlannedRange> {
+    let clamped = |action: RangeAction| {
+        selections
+            .iter()
+            .filter(move |r| r.action == action)
+            .map(|r| TimeRange {
+                start: r.start.max(0.0),
+                end: r.end.min(total_duration),
+            })
+            .filter(|r| r.end > r.start)
+    };
+
+    let mut kept: Vec<TimeRange> = clamped(RangeAction::Keep).collect();
+    if kept.is_empty() {
+        kept.push(TimeRange {
+            start: 0.0,
+            end: total_duration,
+        });
+    }
+    kept.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    // 合并重叠的保留范围
+    let mut merged: Vec<TimeRange> = Vec::new();
+    for range in kept {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    for discard in clamped(RangeAction::Discard) {
+        merged = merged
+            .into_iter()
+            .flat_map(|r| {
+                if discard.end <= r.start || discard.start >= r.end {
+                    return vec![r];
+                }
+                let mut parts = Vec::new();
+                if discard.start > r.start {
+                    parts.push(TimeRange {
+                        start: r.start,
+                        end: discard.start,
+                    });
+                }
+                if discard.end < r.end {
+                    parts.push(TimeRange {
+                        start: discard.end,
+                        end: r.end,
+                    });
+                }
+                parts
+            })
+            .collect();
+    }
+
+    merged
+        .into_iter()
+        .map(|r| PlannedRange {
+            start: r.start,
+            end: r.end,
+            title: None,
+        })
+        .collect()
+}
+
+// 返回各个区间之间的部分，跳过不足一帧的空隙
+fn ranges_between(intervals: &[TimeRange], total_duration: f64) -> Vec<PlannedRange> {
+    const MIN_LENGTH: f64 = 0.04;
+
+    let mut ranges = Vec::new();
+    let mut current = 0.0;
+    for interval in intervals {
+        if interval.start - current > MIN_LENGTH {
+            ranges.push(PlannedRange {
+                start: current,
+                end: interval.start,
+                title: None,
+            });
+        }
+        current = f64::max(current, interval.end);
+    }
+    if total_duration - current > MIN_LENGTH {
+        ranges.push(PlannedRange {
+            start: current,
+            end: total_duration,
+            title: None,
+        });
+    }
+
+    ranges
+}
+
+// 从当前位置起在长度范围内选最接近目标长度的候选分割点
+// 范围内没有候选点时在最大长度处直接切分
+fn window_splits(
+    candidates: &[f64],
+    total_duration: f64,
+    min_length: f64,
+    max_length: f64,
+) -> VideoResult<Vec<f64>> {
+    const EPSILON: f64 = 0.001;
+
+    if max_length <= 0.0 || min_length < 0.0 || min_length > max_length {
+        return Err(VideoError::ProcessingError(
+            "Invalid segment length range".to_string(),
+        ));
+    }
+
+    let target_length = (min_length + max_length) / 2.0;
+    let mut split_points = Vec::new();
+    let mut current = 0.0;
+
+    while total_duration - current > max_length {
+        let remaining = total_duration - current;
+        let target = current + target_length;
+        // 剩余部分放不下一段最长加一段最短时，限制切点使最后一段不短于 min_length
+        let longest = if remaining < max_length + min_length {
+            (remaining - min_length).clamp(min_length, max_length)
+        } else {
+            max_length
+        };
+        // 切点必须在当前位置之后，否则 min_length 为0时会反复选中同一个切点
+        let lowest = current + min_length.max(EPSILON);
+        let highest = current + longest;
+        let cut = candidates
+            .iter()
+            .copied()
+            .filter(|t| (lowest..=highest).contains(t))
+            .min_by(|a, b| (a - target).abs().total_cmp(&(b - target).abs()));
+
+        let cut = match cut {
+            Some(cut) => cut,
+            None => {
+                println!(
+                    "No split point between {:.2}s and {:.2}s, cutting at {:.2}s",
+                    lowest, highest, highest
+                );
+                highest
+            }
+        };
+        split_points.push(cut);
+        current = cut;
+    }
+
+    Ok(split_points)
+}
+
+// 按大小估计分割点时预留的余量，用于容器开销和码率波动
+const SIZE_MARGIN: f64 = 0.95;
+
+fn cut_args(
+    input: &Path,
+    start_time: f64,
+    duration: f64,
+    codec_args: &[String],
+    output: &Path,
+) -> Vec<String> {
+    let mut args = vec![
+        "-y".to_string(), // 只写临时文件，可以直接覆盖
+        "-ss".to_string(),
+        start_time.to_string(), // 先设置开始时间
+        "-i".to_string(),
+        input.to_string_lossy().to_string(),
+        "-t".to_string(),
+        duration.to_string(), // 使用持续时间
+    ];
+    args.extend(codec_args.iter().cloned());
+    args.extend([
+        "-avoid_negative_ts".to_string(),
+        "make_zero".to_string(),
+        output.to_string_lossy().to_string(),
+    ]);
+    args
+}
+
+fn copy_args() -> Vec<String> {
+    ["-c", "copy"].map(String::from).to_vec()
+}
+
+fn reencode_args() -> Vec<String> {
+    [
+        "-c:v", "libx264", // 重新编码视频以确保精确分割
+        "-c:a", "aac", // 重新编码音频
+        "-preset", "fast", // 使用快速预设
+        "-crf", "23", // 质量设置
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn is_joining(request: &SplitRequest) -> bool {
+    matches!(request.split_type, SplitType::Ranges { join: true, .. })
+}
+
+fn joined_output_path(request: &SplitRequest) -> PathBuf {
+    let stem = request
+        .video_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("video");
+    request
+        .output_dir
+        .join(format!("{}_edited.{}", stem, request.output_format))
+}
+
+fn output_path(request: &SplitRequest, segment: &SegmentPlan) -> PathBuf {
+    // 拼接前的各段是中间文件，用隐藏文件名避免与用户文件冲突
+    if is_joining(request) {
+        let stem = request
+            .video_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("video");
+        return request.output_dir.join(format!(
+            ".{}_join_{:03}.{}",
+            stem,
+            segment.index + 1,
+            request.output_format
+        ));
+    }
+
+    // 有标题时附加在序号后面，保证文件名仍按顺序排列
+    let title = segment
+        .title
+        .as_deref()
+        .map(sanitize_file_name)
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("_{}", t))
+        .unwrap_or_default();
+    let output_filename = format!(
+        "segment_{}_{:03}{}.{}",
+        request
+            .video_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("segment"),
+        segment.index + 1,
+        title,
+        request.output_format
+    );
+
+    request.output_dir.join(output_filename)
+}
+
+// 去掉文件名中不允许的字符，并限制长度
+fn sanitize_file_name(name: &str) -> String {
+    const MAX_CHARS: usize = 80;
+
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_CHARS)
+        .collect::<String>()
+        .trim()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+enum OutputTarget {
+    Write(PathBuf),
+    // 按 OverwritePolicy::Skip 保留的已有文件
+    Existing(PathBuf),
+}
+
+// 按覆盖策略决定分段的最终文件名
+fn resolve_output(request: &SplitRequest, path: PathBuf) -> VideoResult<OutputTarget> {
+    // 拼接前的中间文件由我们自己管理，直接覆盖
+    if !path.exists() || (is_joining(request) && path != joined_output_path(request)) {
+        return Ok(OutputTarget::Write(path));
+    }
+
+    match request.overwrite_policy {
+        OverwritePolicy::Overwrite => Ok(OutputTarget::Write(path)),
+        OverwritePolicy::Skip => Ok(OutputTarget::Existing(path)),
+        OverwritePolicy::Suffix => {
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("segment")
+                .to_string();
+            let candidate = (1..)
+                .map(|n| path.with_file_name(format!("{}_{}.{}", stem, n, request.output_format)))
+                .find(|p| !p.exists())
+                .unwrap();
+            Ok(OutputTarget::Write(candidate))
+        }
+        OverwritePolicy::Fail => Err(VideoError::ProcessingError(format!(
+            "Output file already exists: {}",
+            path.display()
+        ))),
+    }
+}
+
+fn part_path(output: &Path) -> PathBuf {
+    let mut name = output.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+// 临时文件写完后改名为最终文件，处理期间出现的同名文件同样遵循覆盖策略
+fn commit_output(part: &Path, output: &Path, policy: OverwritePolicy) -> VideoResult<()> {
+    if policy != OverwritePolicy::Overwrite && output.exists() {
+        return Err(VideoError::ProcessingError(format!(
+            "Output file already exists: {}",
+            output.display()
+        )));
+    }
+    std::fs::rename(part, output)?;
+    Ok(())
+}
+
+// ffmpeg默认映射会带到输出中的流类型，data（时间码、GoPro遥测）和 attachment（字体）流不会被映射
+fn mapped_stream_types(source: Vec<String>, format: &str) -> Vec<String> {
+    source
+        .into_iter()
+        .filter(|t| match t.as_str() {
+            "video" | "audio" => true,
+            // 只有mkv能容纳各种字幕编码，其他容器是否带上字幕取决于字幕编码
+            "subtitle" => format == "mkv",
+            _ => false,
+        })
+        .collect()
+}
+
+// 输出格式（扩展名）对应的ffmpeg封装格式名
+fn muxer_name(format: &str) -> &str {
+    match format {
+        "mkv" => "matroska",
+        "ts" => "mpegts",
+        "m4v" => "mp4",
+        "wmv" => "asf",
+        other => other,
+    }
+}
+
+// ffmpeg的错误信息一般在输出的最后几行
+fn stderr_excerpt(stderr: &str) -> String {
+    const MAX_LINES: usize = 10;
+
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(MAX_LINES)..].join("\n")
+}
+
+fn snap_to_keyframe(time: f64, keyframes: &[f64]) -> f64 {
+    keyframes
+        .iter()
+        .copied()
+        .min_by(|a, b| (a - time).abs().total_cmp(&(b - time).abs()))
+        .unwrap_or(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planned(bounds: &[f64]) -> Vec<PlannedRange> {
+        bounds
+            .windows(2)
+            .map(|w| PlannedRange {
+                start: w[0],
+                end: w[1],
+                title: None,
+            })
+            .collect()
+    }
+
+    fn spans(ranges: &[PlannedRange]) -> Vec<(f64, f64)> {
+        ranges.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn apply_constraints_merges_short_tail_backwards() {
+        let constraints = SegmentConstraints {
+            min_length: Some(5.0),
+            ..Default::default()
+        };
+        let ranges = apply_constraints(planned(&[0.0, 60.0, 61.0]), &constraints, 61.0).unwrap();
+        assert_eq!(spans(&ranges), vec![(0.0, 61.0)]);
+    }
+
+    #[test]
+    fn apply_constraints_stops_merging_once_long_enough() {
+        let constraints = SegmentConstraints {
+            min_length: Some(10.0),
+            ..Default::default()
+        };
+        let bounds: Vec<f64> = (0..=15).map(|i| i as f64 * 2.0).collect();
+        let ranges = apply_constraints(planned(&bounds), &constraints, 30.0).unwrap();
+        assert_eq!(
+            spans(&ranges),
+            vec![(0.0, 10.0), (10.0, 20.0), (20.0, 30.0)]
+        );
+    }
+
+    #[test]
+    fn window_splits_zero_min_length_advances() {
+        let points = window_splits(&[10.0], 200.0, 0.0, 60.0).unwrap();
+        assert_eq!(points, vec![10.0, 70.0, 130.0, 190.0]);
+    }
+
+    #[test]
+    fn window_splits_keeps_tail_above_min_length() {
+        let points = window_splits(&[35.0, 45.0], 65.0, 30.0, 60.0).unwrap();
+        assert_eq!(points, vec![35.0]);
+    }
 
-        Ok(output_files)
+    #[test]
+    fn window_splits_rejects_negative_min_length() {
+        assert!(window_splits(&[10.0], 200.0, -1.0, 60.0).is_err());
     }
 }
@@ -0,0 +1,240 @@
+use crate::services::cancel::CancelToken;
+use crate::utils::{VideoError, VideoResult};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+
+// 一条字幕，时间单位为秒
+#[derive(Debug, Clone)]
+pub struct SubtitleCue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+// 读取 SRT、WebVTT 或 ASS/SSA 字幕文件
+pub fn load_file(path: &Path) -> VideoResult<Vec<SubtitleCue>> {
+    if !path.exists() {
+        return Err(VideoError::FileNotFound(path.display().to_string()));
+    }
+
+    let bytes = std::fs::read(path)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    parse(&content, extension.as_deref())
+}
+
+// 把视频中的第 stream_index 条字幕流转换为SRT后解析
+pub async fn extract_stream(
+    ffmpeg_path: &str,
+    video_path: &Path,
+    stream_index: usize,
+    cancel: &CancelToken,
+) -> VideoResult<Vec<SubtitleCue>> {
+    let child = Command::new(ffmpeg_path)
+        .args(["-v", "error", "-i"])
+        .arg(video_path)
+        .args(["-map", &format!("0:s:{}", stream_index), "-f", "srt", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+
+    let output = tokio::select! {
+        output = child.wait_with_output() => output?,
+        _ = cancel.cancelled() => return Err(VideoError::Cancelled),
+    };
+
+    if !output.status.success() {
+        return Err(VideoError::FFmpegError(format!(
+            "Failed to extract subtitle stream {}: {}",
+            stream_index,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    parse(&String::from_utf8_lossy(&output.stdout), Some("srt"))
+}
+
+// 优先按扩展名判断格式，否则根据内容判断
+pub fn parse(content: &str, extension: Option<&str>) -> VideoResult<Vec<SubtitleCue>> {
+    let content = content.trim_start_matches('\u{feff}');
+    let is_ass = match extension {
+        Some("ass") | Some("ssa") => true,
+        Some("srt") | Some("vtt") => false,
+        _ => content.trim_start().starts_with("[Script Info]"),
+    };
+
+    let mut cues = if is_ass {
+        parse_ass(content)
+    } else {
+        // SRT与WebVTT都是以空行分隔、带 --> 时间行的块
+        parse_blocks(content)
+    };
+    cues.retain(|c| c.end > c.start);
+    cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    if cues.is_empty() {
+        return Err(VideoError::InvalidFormat(
+            "No subtitle cues found".to_string(),
+        ));
+    }
+
+    Ok(cues)
+}
+
+fn parse_blocks(content: &str) -> Vec<SubtitleCue> {
+    let mut cues = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = line.split_once("-->") else {
+            continue;
+        };
+        // WebVTT的时间后面可能带有 align:start 等设置
+        let start = parse_timestamp(start.trim());
+        let end = end.split_whitespace().next().and_then(parse_timestamp);
+        let (Some(start), Some(end)) = (start, end) else {
+            continue;
+        };
+
+        let text: Vec<String> = lines
+            .by_ref()
+            .take_while(|l| !l.trim().is_empty())
+            .map(strip_tags)
+            .collect();
+        cues.push(SubtitleCue {
+            start,
+            end,
+            text: text.join(" ").trim().to_string(),
+        });
+    }
+
+    cues
+}
+
+const DEFAULT_ASS_COLUMNS: [&str; 10] = [
+    "layer", "start", "end", "style", "name", "marginl", "marginr", "marginv", "effect", "text",
+];
+
+fn parse_ass(content: &str) -> Vec<SubtitleCue> {
+    let mut cues = Vec::new();
+    let mut in_events = false;
+    let mut columns: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[Events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+
+        if let Some(format) = line.strip_prefix("Format:") {
+            columns = format.split(',').map(|c| c.trim().to_lowercase()).collect();
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            // 缺少 Format 行时使用ASS的默认列
+            if columns.is_empty() {
+                columns = DEFAULT_ASS_COLUMNS.map(String::from).to_vec();
+            }
+            // 最后一列是文本，文本中可能包含逗号
+            let fields: Vec<&str> = dialogue.splitn(columns.len().max(1), ',').collect();
+            let field = |name: &str| {
+                columns
+                    .iter()
+                    .position(|c| c == name)
+                    .and_then(|i| fields.get(i))
+                    .map(|f| f.trim())
+            };
+            let start = field("start").and_then(parse_timestamp);
+            let end = field("end").and_then(parse_timestamp);
+            if let (Some(start), Some(end)) = (start, end) {
+                let text = field("text").unwrap_or_default().replace("\\N", " ");
+                cues.push(SubtitleCue {
+                    start,
+                    end,
+                    text: strip_tags(&text).trim().to_string(),
+                });
+            }
+        }
+    }
+
+    cues
+}
+
+// 支持 01:02:03,456、01:02:03.45 和 02:03.456 几种写法
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let mut seconds = 0.0;
+    for part in value.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+// 去掉 <i>、{\an8} 之类的格式标记
+fn strip_tags(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut closing = None;
+    for c in text.chars() {
+        match (closing, c) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (None, c) => result.push(c),
+            (Some(end), c) if c == end => closing = None,
+            (Some(_), _) => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(cues: &[SubtitleCue]) -> Vec<(f64, f64, &str)> {
+        cues.iter()
+            .map(|c| (c.start, c.end, c.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_srt_with_comma_times() {
+        let srt = "1\n00:00:01,500 --> 00:00:03,000\n<i>Hello</i>\nworld\n\n2\n00:01:00,000 --> 00:01:02,250\nBye\n";
+        let cues = parse(srt, Some("srt")).unwrap();
+        assert_eq!(
+            spans(&cues),
+            vec![(1.5, 3.0, "Hello world"), (60.0, 62.25, "Bye")]
+        );
+    }
+
+    #[test]
+    fn parse_vtt_with_cue_settings() {
+        let vtt = "WEBVTT\n\n00:05.000 --> 00:07.500 align:start position:10%\n{\\an8}Top line\n\nnote\n01:00:00.000 --> 01:00:01.000\nLate\n";
+        let cues = parse(vtt, Some("vtt")).unwrap();
+        assert_eq!(
+            spans(&cues),
+            vec![(5.0, 7.5, "Top line"), (3600.0, 3601.0, "Late")]
+        );
+    }
+
+    #[test]
+    fn parse_ass_text_with_commas() {
+        let ass = "[Script Info]\nTitle: Test\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:02.00,0:00:04.50,Default,,0,0,0,,Well, hello,\\Nthere\n";
+        let cues = parse(ass, None).unwrap();
+        assert_eq!(spans(&cues), vec![(2.0, 4.5, "Well, hello, there")]);
+    }
+
+    #[test]
+    fn parse_rejects_empty_input() {
+        assert!(parse("no cues here", Some("srt")).is_err());
+    }
+}
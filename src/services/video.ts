@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { VideoInfo, SplitRequest, SplitResult, ScenePoint } from '../types/video';
+import { VideoInfo, SplitRequest, SplitResult, ScenePoint, JobInfo, SplitType, AudioMatch, SceneDetector, SceneCurve } from '../types/video';
 
 export class VideoService {
   static async getFilePath(_file: File): Promise<string | null> {
@@ -34,12 +34,73 @@ export class VideoService {
     }
   }
 
-  static async detectScenes(filePath: string, threshold: number = 0.3): Promise<ScenePoint[]> {
+  static async cancelSplit(jobId?: number): Promise<boolean> {
     try {
-      const scenes = await invoke<ScenePoint[]>('detect_scenes', { 
-        video_path: filePath, 
-        threshold,
-        min_duration: 2.0
+      return await invoke<boolean>('cancel_split', { jobId });
+    } catch (error) {
+      console.error('取消分割失败:', error);
+      return false;
+    }
+  }
+
+  static async enqueueSplit(request: SplitRequest, priority: number = 0): Promise<number> {
+    try {
+      return await invoke<number>('enqueue_split', { request, priority });
+    } catch (error) {
+      console.error('添加分割任务失败:', error);
+      throw new Error('添加分割任务失败');
+    }
+  }
+
+  static async listJobs(): Promise<JobInfo[]> {
+    try {
+      return await invoke<JobInfo[]>('list_jobs');
+    } catch (error) {
+      console.error('获取任务列表失败:', error);
+      return [];
+    }
+  }
+
+  static async getJob(jobId: number): Promise<JobInfo> {
+    try {
+      return await invoke<JobInfo>('get_job', { jobId });
+    } catch (error) {
+      console.error('获取任务失败:', error);
+      throw new Error('获取任务失败');
+    }
+  }
+
+  static async resumeJob(jobId: number): Promise<JobInfo> {
+    try {
+      return await invoke<JobInfo>('resume_job', { jobId });
+    } catch (error) {
+      console.error('继续任务失败:', error);
+      throw new Error('继续任务失败');
+    }
+  }
+
+  static async removeJob(jobId: number): Promise<boolean> {
+    try {
+      return await invoke<boolean>('remove_job', { jobId });
+    } catch (error) {
+      console.error('删除任务失败:', error);
+      return false;
+    }
+  }
+
+  static async detectScenes(
+    filePath: string,
+    threshold: number = 0.3,
+    detector: SceneDetector = 'ffmpeg'
+  ): Promise<ScenePoint[]> {
+    try {
+      const scenes = await invoke<ScenePoint[]>('detect_scenes', {
+        request: {
+          video_path: filePath,
+          threshold,
+          min_duration: 2.0,
+          detector,
+        },
       });
       return scenes;
     } catch (error) {
@@ -48,6 +109,75 @@ export class VideoService {
     }
   }
 
+  static async getSceneCurve(
+    filePath: string,
+    detector: SceneDetector = 'ffmpeg',
+    maxPoints: number = 2000
+  ): Promise<SceneCurve> {
+    try {
+      return await invoke<SceneCurve>('get_scene_curve', {
+        request: {
+          video_path: filePath,
+          detector,
+          max_points: maxPoints,
+        },
+      });
+    } catch (error) {
+      console.error('场景分数分析失败:', error);
+      throw new Error('场景分数分析失败');
+    }
+  }
+
+  // 使用已分析的分数重新计算切点，可以在拖动阈值时反复调用
+  static async rethresholdScenes(
+    filePath: string,
+    threshold: number,
+    minDurationMs: number = 2000,
+    detector: SceneDetector = 'ffmpeg'
+  ): Promise<ScenePoint[]> {
+    try {
+      return await invoke<ScenePoint[]>('rethreshold_scenes', {
+        request: {
+          video_path: filePath,
+          detector,
+          threshold,
+          min_duration_ms: minDurationMs,
+        },
+      });
+    } catch (error) {
+      console.error('重新计算场景切点失败:', error);
+      throw new Error('重新计算场景切点失败');
+    }
+  }
+
+  static async detectJingles(
+    filePath: string,
+    referencePath: string,
+    minScore: number = 0.7
+  ): Promise<AudioMatch[]> {
+    try {
+      return await invoke<AudioMatch[]>('detect_jingles', {
+        request: {
+          video_path: filePath,
+          reference_path: referencePath,
+          min_score: minScore,
+        },
+      });
+    } catch (error) {
+      console.error('片头音频匹配失败:', error);
+      throw new Error('片头音频匹配失败');
+    }
+  }
+
+  static async parseTimestamps(text: string): Promise<SplitType> {
+    try {
+      return await invoke<SplitType>('parse_timestamps', { text });
+    } catch (error) {
+      console.error('解析时间戳失败:', error);
+      throw new Error('解析时间戳失败');
+    }
+  }
+
   static async selectVideoFile(): Promise<string | null> {
     try {
       const filePath = await invoke<string>('select_video_file');
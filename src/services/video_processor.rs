@@ -1,16 +1,28 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 use serde_json::Value;
+use tauri::WebviewWindow;
 use crate::models::*;
-use crate::utils::{VideoResult, VideoError, validate_video_file};
+use crate::services::cancel::CancelToken;
+use crate::services::audio_match;
+use crate::services::scene_cache::SceneCache;
+use crate::services::scene_detector;
+use crate::services::ffmpeg::{decode_audio, run_with_progress, run_with_stderr_lines, FfmpegProgress};
+use crate::services::progress::{emit_analysis_progress, ANALYSIS_PROGRESS_EVENT, SCENE_PROGRESS_EVENT};
+use crate::utils::{VideoResult, VideoError, validate_video_file, value_after};
+
+// ffmpeg scene 分数的分析参数，修改滤镜时需要同时修改，使旧缓存失效
+const FFMPEG_SCENE_ANALYSIS: &str = "ffmpeg-scene-v1";
 
 pub struct VideoProcessor {
     ffmpeg_path: String,
     ffprobe_path: String,
+    scene_cache: Arc<SceneCache>,
 }
 
 impl VideoProcessor {
-    pub fn new() -> Self {
+    pub fn new(cache_dir: PathBuf) -> Self {
         // 尝试不同的FFmpeg路径
         let ffmpeg_path = Self::find_ffmpeg_path();
         let ffprobe_path = Self::find_ffprobe_path();
@@ -21,6 +33,7 @@ impl VideoProcessor {
         Self {
             ffmpeg_path,
             ffprobe_path,
+            scene_cache: Arc::new(SceneCache::new(cache_dir)),
         }
     }
     
@@ -78,6 +91,7 @@ impl VideoProcessor {
                 "-print_format", "json",
                 "-show_format",
                 "-show_streams",
+                "-show_chapters",
                 path,
             ])
             .output()
@@ -168,6 +182,8 @@ impl VideoProcessor {
             .unwrap_or("unknown")
             .to_string();
         
+        let chapters = self.parse_chapters(json);
+        
         Ok(VideoInfo {
             path: path_obj.to_path_buf(),
             filename,
@@ -178,56 +194,357 @@ impl VideoProcessor {
             bitrate,
             format: format_name.to_string(),
             size,
+            chapters,
         })
     }
     
-    pub async fn detect_scenes(&self, video_path: &str, threshold: f32, min_duration: f64) -> VideoResult<Vec<ScenePoint>> {
-        println!("Detecting scenes with threshold: {}, min_duration: {}", threshold, min_duration);
+    fn parse_chapters(&self, json: &Value) -> Vec<Chapter> {
+        let Some(chapters) = json.get("chapters").and_then(|c| c.as_array()) else {
+            return Vec::new();
+        };
         
-        let output = Command::new(&self.ffmpeg_path)
-            .args([
-                "-i", video_path,
-                "-vf", &format!("select='gt(scene,{})',showinfo", threshold),
-                "-f", "null",
-                "-",
-            ])
-            .output()
-            .map_err(|e| VideoError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+        let parse_time = |chapter: &Value, key: &str| {
+            chapter.get(key)
+                .and_then(|t| t.as_str())
+                .and_then(|s| s.parse::<f64>().ok())
+        };
+        
+        chapters
+            .iter()
+            .filter_map(|chapter| Some((parse_time(chapter, "start_time")?, parse_time(chapter, "end_time")?, chapter)))
+            .enumerate()
+            .map(|(index, (start, end, chapter))| Chapter {
+                index,
+                start,
+                end,
+                title: chapter.get("tags")
+                    .and_then(|t| t.get("title"))
+                    .and_then(|t| t.as_str())
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty()),
+            })
+            .collect()
+    }
+    
+    pub async fn detect_scenes(
+        &self,
+        video_path: &str,
+        detector: &SceneDetector,
+        threshold: f32,
+        min_duration: f64,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<ScenePoint>> {
+        println!(
+            "Detecting scenes with {:?} detector, threshold: {}, min_duration: {}",
+            detector, threshold, min_duration
+        );
+        
+        let scores = self.scene_scores(video_path, detector, window, cancel).await?;
+        Ok(scenes_from_scores(&scores, detector, threshold, min_duration))
+    }
+    
+    // 用已保存的分数重新判断切点，不运行ffmpeg，供界面调整阈值时使用
+    pub async fn rethreshold_scenes(
+        &self,
+        video_path: &str,
+        detector: &SceneDetector,
+        threshold: f32,
+        min_duration: f64,
+    ) -> VideoResult<Vec<ScenePoint>> {
+        let key = self.scene_cache_key(video_path, detector).await?;
+        let scores = self.scene_cache.load(&key).ok_or_else(|| {
+            VideoError::ProcessingError(format!(
+                "Scene scores for {} have not been analyzed yet",
+                video_path
+            ))
+        })?;
+        Ok(scenes_from_scores(&scores, detector, threshold, min_duration))
+    }
+    
+    // 分析一次并返回降采样到最多 max_points 个点的分数曲线
+    pub async fn scene_curve(
+        &self,
+        video_path: &str,
+        detector: &SceneDetector,
+        max_points: usize,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<SceneCurve> {
+        let scores = self.scene_scores(video_path, detector, window, cancel).await?;
+        Ok(SceneCurve {
+            frame_count: scores.len(),
+            duration: scores.last().map(|f| f.time).unwrap_or(0.0),
+            points: downsample_scores(&scores, max_points),
+        })
+    }
+    
+    // 取得检测器所需的逐帧分数，同一文件和分析参数只分析一次
+    pub async fn scene_scores(
+        &self,
+        video_path: &str,
+        detector: &SceneDetector,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<FrameScore>> {
+        let key = self.scene_cache_key(video_path, detector).await?;
+        if let Some(scores) = self.scene_cache.load(&key) {
+            println!("Using cached scene scores for {} ({})", video_path, key);
+            return Ok(scores);
+        }
+        
+        let scores = match detector {
+            SceneDetector::Ffmpeg => self.analyze_scene_scores(video_path, window, cancel).await?,
+            _ => self.analyze_frames(video_path, window, cancel).await?,
+        };
+        self.scene_cache.store(&key, &scores);
+        Ok(scores)
+    }
+    
+    async fn scene_cache_key(&self, video_path: &str, detector: &SceneDetector) -> VideoResult<String> {
+        let path = Path::new(video_path).to_path_buf();
+        validate_video_file(&path)?;
         
-        // FFmpeg的showinfo输出在stderr中
-        let stderr_output = String::from_utf8_lossy(&output.stderr);
-        println!("FFmpeg stderr output: {}", stderr_output);
+        // 除 ffmpeg 外的检测器共用本地计算的分数
+        let analysis = match detector {
+            SceneDetector::Ffmpeg => FFMPEG_SCENE_ANALYSIS,
+            _ => scene_detector::ANALYSIS,
+        };
+        // 第一次计算需要读取整个文件
+        let cache = Arc::clone(&self.scene_cache);
+        tokio::task::spawn_blocking(move || cache.key(&path, analysis))
+            .await
+            .map_err(|e| VideoError::ProcessingError(format!("Failed to hash video file: {}", e)))?
+    }
+    
+    // 用本地检测器计算每一帧的内容变化分数
+    pub async fn analyze_frames(
+        &self,
+        video_path: &str,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<FrameScore>> {
+        let info = self.get_video_info(video_path).await?;
+        scene_detector::analyze_frames(
+            &self.ffmpeg_path,
+            Path::new(video_path),
+            info.fps,
+            info.duration,
+            window,
+            cancel,
+        )
+        .await
+    }
+    
+    // 计算每一帧的场景变化分数，不做阈值判断
+    pub async fn analyze_scene_scores(
+        &self,
+        video_path: &str,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<FrameScore>> {
+        let duration = self.get_video_info(video_path).await?.duration;
+        let args = [
+            "-i", video_path,
+            "-an",
+            "-vf", "select='gte(scene,0)',metadata=print,showinfo",
+            "-f", "null",
+            "-",
+        ].map(String::from);
         
-        self.parse_scene_detection_output(&output.stderr, min_duration)
+        // 每帧都有几行日志，边读边解析，不保存整个stderr
+        let mut scores = Vec::new();
+        let mut pending_score = None;
+        let output = run_with_stderr_lines(
+            &self.ffmpeg_path,
+            &args,
+            cancel,
+            |p| {
+                if let Some(window) = window {
+                    emit_analysis_progress(window, SCENE_PROGRESS_EVENT, "Detecting scenes", duration, p);
+                }
+            },
+            |line| parse_scene_score_line(line, &mut pending_score, &mut scores),
+        )
+        .await?;
+        
+        if !output.success {
+            return Err(VideoError::FFmpegError(format!(
+                "Scene detection failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        
+        Ok(scores)
     }
     
-    fn parse_scene_detection_output(&self, output: &[u8], min_duration: f64) -> VideoResult<Vec<ScenePoint>> {
+    pub async fn detect_silence(
+        &self,
+        video_path: &str,
+        noise_db: f64,
+        min_silence: f64,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<TimeRange>> {
+        println!("Detecting silence with noise: {}dB, min_silence: {}", noise_db, min_silence);
+        
+        let duration = self.get_video_info(video_path).await?.duration;
+        let args = [
+            "-i", video_path,
+            "-vn",
+            "-af", &format!("silencedetect=noise={}dB:d={}", noise_db, min_silence),
+            "-f", "null",
+            "-",
+        ].map(String::from);
+        
+        let output = run_with_progress(&self.ffmpeg_path, &args, cancel, |p| {
+            if let Some(window) = window {
+                emit_analysis_progress(window, ANALYSIS_PROGRESS_EVENT, "Detecting silence", duration, p);
+            }
+        })
+        .await?;
+        
+        if !output.success {
+            return Err(VideoError::FFmpegError(format!(
+                "Silence detection failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        
+        Ok(self.parse_silence_output(&output.stderr, duration))
+    }
+    
+    // silencedetect 在stderr中输出 silence_start: 和 silence_end: 两种行
+    fn parse_silence_output(&self, output: &[u8], duration: f64) -> Vec<TimeRange> {
         let output_str = String::from_utf8_lossy(output);
-        let mut scenes = Vec::new();
-        let mut last_time = 0.0;
+        let mut silences = Vec::new();
+        let mut current_start = None;
         
         for line in output_str.lines() {
-            if line.contains("pts_time:") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for i in 0..parts.len() - 1 {
-                    if parts[i] == "pts_time:" {
-                        if let Ok(time) = parts[i + 1].parse::<f64>() {
-                            if time - last_time >= min_duration {
-                                scenes.push(ScenePoint {
-                                    time,
-                                    confidence: 1.0,
-                                    frame_number: 0,
-                                });
-                                last_time = time;
-                            }
-                        }
-                        break;
-                    }
+            if let Some(start) = value_after(line, "silence_start:") {
+                current_start = Some(start.max(0.0));
+            } else if let Some(end) = value_after(line, "silence_end:") {
+                if let Some(start) = current_start.take() {
+                    silences.push(TimeRange { start, end });
                 }
             }
         }
         
-        Ok(scenes)
+        // 文件在静音中结束时没有 silence_end
+        if let Some(start) = current_start {
+            silences.push(TimeRange { start, end: duration });
+        }
+        
+        silences
+    }
+    
+    pub async fn detect_black_frames(
+        &self,
+        video_path: &str,
+        pixel_threshold: f64,
+        picture_threshold: f64,
+        min_duration: f64,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<TimeRange>> {
+        println!(
+            "Detecting black frames with pix_th: {}, pic_th: {}, min_duration: {}",
+            pixel_threshold, picture_threshold, min_duration
+        );
+        
+        let duration = self.get_video_info(video_path).await?.duration;
+        let args = [
+            "-i", video_path,
+            "-an",
+            "-vf", &format!(
+                "blackdetect=d={}:pix_th={}:pic_th={}",
+                min_duration, pixel_threshold, picture_threshold
+            ),
+            "-f", "null",
+            "-",
+        ].map(String::from);
+        
+        let output = run_with_progress(&self.ffmpeg_path, &args, cancel, |p| {
+            if let Some(window) = window {
+                emit_analysis_progress(window, ANALYSIS_PROGRESS_EVENT, "Detecting black frames", duration, p);
+            }
+        })
+        .await?;
+        
+        if !output.success {
+            return Err(VideoError::FFmpegError(format!(
+                "Black frame detection failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        
+        // blackdetect 每个区间输出一行 black_start:a black_end:b black_duration:c
+        let output_str = String::from_utf8_lossy(&output.stderr);
+        let intervals = output_str
+            .lines()
+            .filter_map(|line| {
+                Some(TimeRange {
+                    start: value_after(line, "black_start:")?,
+                    end: value_after(line, "black_end:")?,
+                })
+            })
+            .collect();
+        
+        Ok(intervals)
+    }
+    
+    pub async fn detect_jingles(
+        &self,
+        video_path: &str,
+        reference_path: &Path,
+        min_score: f32,
+        window: Option<&WebviewWindow>,
+        cancel: Option<&CancelToken>,
+    ) -> VideoResult<Vec<AudioMatch>> {
+        println!("Matching reference audio {} with min_score: {}", reference_path.display(), min_score);
+        
+        if !reference_path.exists() {
+            return Err(VideoError::FileNotFound(reference_path.to_string_lossy().to_string()));
+        }
+        
+        // 解码没有ffmpeg进度输出，只报告所处的阶段
+        let duration = self.get_video_info(video_path).await?.duration;
+        let report = |message: &str, done: f64| {
+            if let Some(window) = window {
+                let progress = FfmpegProgress { out_time: duration * done, speed: None };
+                emit_analysis_progress(window, ANALYSIS_PROGRESS_EVENT, message, duration, &progress);
+            }
+        };
+        
+        report("Decoding audio", 0.0);
+        let reference = decode_audio(&self.ffmpeg_path, reference_path, audio_match::SAMPLE_RATE, cancel).await?;
+        let source = decode_audio(&self.ffmpeg_path, Path::new(video_path), audio_match::SAMPLE_RATE, cancel).await?;
+        
+        report("Matching reference audio", 0.5);
+        let matches = tokio::task::spawn_blocking(move || {
+            let reference = audio_match::spectral_features(&reference);
+            let source = audio_match::spectral_features(&source);
+            let matches = audio_match::find_matches(&source, &reference, min_score);
+            (matches, reference.len())
+        })
+        .await
+        .map_err(|e| VideoError::ProcessingError(format!("Audio matching failed: {}", e)))?;
+        report("Matching reference audio", 1.0);
+        
+        let (matches, reference_frames) = matches;
+        if reference_frames == 0 {
+            return Err(VideoError::InvalidFormat("Reference audio is too short".to_string()));
+        }
+        
+        let frame_duration = audio_match::frame_duration();
+        Ok(matches
+            .into_iter()
+            .map(|(frame, score)| AudioMatch {
+                time: frame as f64 * frame_duration,
+                duration: reference_frames as f64 * frame_duration,
+                confidence: score,
+            })
+            .collect())
     }
     
     pub async fn extract_thumbnail(&self, video_path: &str, time: f64, output_path: &str) -> VideoResult<String> {
@@ -251,4 +568,84 @@ impl VideoProcessor {
         
         Ok(output_path.to_string())
     }
-}
\ No newline at end of file
+}
+
+// metadata=print 先输出 lavfi.scene_score=x，随后 showinfo 输出同一帧的 n:、pts: 和 pts_time:
+fn parse_scene_score_line(line: &str, pending_score: &mut Option<f32>, scores: &mut Vec<FrameScore>) {
+    if let Some(score) = line.split("lavfi.scene_score=").nth(1) {
+        *pending_score = score.trim().parse::<f32>().ok();
+        return;
+    }
+    if !line.contains("showinfo") {
+        return;
+    }
+    
+    let frame = value_after(line, " n:");
+    let pts = value_after(line, " pts:");
+    let time = value_after(line, "pts_time:");
+    // mean:[Y U V] 的第一个值是亮度平面的平均值
+    let luma = value_after(line, "mean:[").unwrap_or(0.0);
+    if let (Some(frame), Some(pts), Some(time)) = (frame, pts, time) {
+        scores.push(FrameScore {
+            frame_number: frame as u64,
+            pts: pts as i64,
+            time,
+            score: pending_score.take().unwrap_or(0.0),
+            luma: (luma / 255.0) as f32,
+        });
+    }
+}
+
+// 按检测器的规则从逐帧分数得到场景切点
+pub fn scenes_from_scores(
+    scores: &[FrameScore],
+    detector: &SceneDetector,
+    threshold: f32,
+    min_duration: f64,
+) -> Vec<ScenePoint> {
+    match detector {
+        SceneDetector::Ffmpeg | SceneDetector::Content => {
+            threshold_scenes(scores, threshold, min_duration)
+        }
+        SceneDetector::Adaptive { window, min_score } => {
+            scene_detector::adaptive_scenes(scores, threshold, *window, *min_score, min_duration)
+        }
+        SceneDetector::Transitions { window } => {
+            scene_detector::transition_scenes(scores, threshold, *window, min_duration)
+        }
+    }
+}
+
+// 把分数按帧均匀分组，每组保留分数最高的一帧，这样降采样后不会丢掉切点处的尖峰
+pub fn downsample_scores(scores: &[FrameScore], max_points: usize) -> Vec<FrameScore> {
+    if max_points == 0 || scores.len() <= max_points {
+        return scores.to_vec();
+    }
+    
+    scores
+        .chunks(scores.len().div_ceil(max_points))
+        .filter_map(|chunk| chunk.iter().max_by(|a, b| a.score.total_cmp(&b.score)))
+        .cloned()
+        .collect()
+}
+
+// 分数超过阈值、且与上一个切点间隔不少于 min_duration 秒的帧作为场景切点
+pub fn threshold_scenes(scores: &[FrameScore], threshold: f32, min_duration: f64) -> Vec<ScenePoint> {
+    let mut scenes = Vec::new();
+    let mut last_time = 0.0;
+    
+    for frame in scores {
+        if frame.score > threshold && frame.time - last_time >= min_duration {
+            scenes.push(ScenePoint {
+                time: frame.time,
+                pts: frame.pts,
+                confidence: frame.score,
+                frame_number: frame.frame_number,
+                transition: TransitionType::Cut,
+            });
+            last_time = frame.time;
+        }
+    }
+    
+    scenes
+}
@@ -8,6 +8,14 @@ export interface VideoInfo {
   bitrate: number;
   format: string;
   size: number;
+  chapters?: Chapter[];
+}
+
+export interface Chapter {
+  index: number;
+  start: number;
+  end: number;
+  title: string | null;
 }
 
 export interface SplitProgress {
@@ -15,31 +23,191 @@ export interface SplitProgress {
   total: number;
   message: string;
   percentage: number;
+  segment_percentage?: number;
+  speed?: number | null;
+  eta?: number | null;
 }
 
 export interface SplitResult {
   success: boolean;
+  cancelled: boolean;
   output_files: string[];
   errors: string[];
   processing_time: number;
+  segments: SegmentResult[];
+}
+
+export interface SegmentPlan {
+  index: number;
+  requested_start: number;
+  requested_end: number;
+  start: number;
+  end: number;
+  title?: string | null;
+}
+
+export interface SegmentResult {
+  index: number;
+  requested_start: number;
+  requested_end: number;
+  start: number;
+  end: number;
+  title?: string | null;
+  output_file: string;
+  status: SegmentStatus;
+  attempts: number;
+  error: string | null;
+  verification: VerificationReport | null;
+}
+
+export interface VerificationReport {
+  passed: boolean;
+  expected_duration: number;
+  duration: number | null;
+  streams: string[];
+  missing_streams: string[];
+  decode_errors: string | null;
+  issues: string[];
 }
 
+export type SegmentStatus = 'completed' | 'failed' | 'skipped' | 'cancelled';
+
 export interface SplitRequest {
   video_path: string;
   output_dir: string;
   output_format: string;
   split_type: SplitType;
+  split_mode?: SplitMode;
+  workers?: number;
+  failure_policy?: FailurePolicy;
+  verify?: VerifyOptions;
+  overwrite_policy?: OverwritePolicy;
+  constraints?: SegmentConstraints;
+}
+
+export interface SegmentConstraints {
+  min_length?: number | null;
+  max_length?: number | null;
+  pre_roll?: number;
+  post_roll?: number;
 }
 
+export type OverwritePolicy = 'overwrite' | 'skip' | 'suffix' | 'fail';
+
+export interface VerifyOptions {
+  duration_tolerance?: number;
+  decode_check?: boolean;
+}
+
+export type FailurePolicy =
+  | 'stop'
+  | { continue: { retries: number; backoff_ms: number } };
+
+export type SplitMode = 'copy' | 'reencode' | 'smart';
+
 export type SplitType = 
   | { time: { duration: number; count?: number } }
-  | { scenes: { threshold: number; min_duration?: number } }
-  | { manual: { split_points: number[] } };
+  | {
+      scenes: {
+        threshold: number;
+        min_duration?: number;
+        detector?: SceneDetector;
+        group?: SceneGrouping | null;
+      };
+    }
+  | { manual: { split_points: number[]; titles?: string[] } }
+  | { size: { max_bytes: number } }
+  | { chapters: { indices?: number[] } }
+  | {
+      silence: {
+        noise_db?: number;
+        min_silence?: number;
+        min_length: number;
+        max_length: number;
+      };
+    }
+  | {
+      black_frames: {
+        pixel_threshold?: number;
+        picture_threshold?: number;
+        min_duration?: number;
+        drop_black?: boolean;
+      };
+    }
+  | { subtitles: { source: SubtitleSource; target_duration?: number } }
+  | { ranges: { ranges: RangeSelection[]; join?: boolean } }
+  | { jingle: { reference_path: string; min_score?: number } };
+
+export interface RangeSelection {
+  start: number;
+  end: number;
+  action: 'keep' | 'discard';
+}
+
+export type SceneDetector =
+  | 'ffmpeg'
+  | 'content'
+  | { adaptive: { window: number; min_score: number } }
+  | { transitions: { window: number } };
+
+export interface SceneGrouping {
+  min_length: number;
+  max_length: number;
+}
+
+export type SubtitleSource =
+  | { file: { path: string } }
+  | { stream: { index: number } };
+
+export interface TimeRange {
+  start: number;
+  end: number;
+}
+
+export interface AudioMatch {
+  time: number;
+  duration: number;
+  confidence: number;
+}
 
 export interface ScenePoint {
   time: number;
+  pts: number;
   confidence: number;
   frame_number: number;
+  transition?: TransitionType;
+}
+
+export interface SceneCurve {
+  frame_count: number;
+  duration: number;
+  points: FrameScore[];
+}
+
+export type TransitionType = 'cut' | 'fade_out' | 'fade_in' | 'dissolve';
+
+export interface FrameScore {
+  frame_number: number;
+  pts: number;
+  time: number;
+  score: number;
+  luma?: number;
+}
+
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled' | 'interrupted';
+
+export interface JobInfo {
+  id: number;
+  status: JobStatus;
+  priority: number;
+  request: SplitRequest;
+  plan: SegmentPlan[];
+  completed_segments: SegmentResult[];
+  result: SplitResult | null;
+  error: string | null;
+  created_at: number;
+  started_at: number | null;
+  finished_at: number | null;
 }
 
 export interface VideoFile {
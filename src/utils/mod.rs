@@ -8,6 +8,7 @@ pub enum VideoError {
     InvalidFormat(String),
     ProcessingError(String),
     IoError(std::io::Error),
+    Cancelled,
 }
 
 impl fmt::Display for VideoError {
@@ -18,6 +19,7 @@ impl fmt::Display for VideoError {
             VideoError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
             VideoError::ProcessingError(msg) => write!(f, "Processing error: {}", msg),
             VideoError::IoError(err) => write!(f, "IO error: {}", err),
+            VideoError::Cancelled => write!(f, "Operation cancelled"),
         }
     }
 }
@@ -32,6 +34,12 @@ impl From<std::io::Error> for VideoError {
 
 pub type VideoResult<T> = Result<T, VideoError>;
 
+pub fn cpu_core_count() -> usize {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_cpu();
+    sys.cpus().len().max(1)
+}
+
 #[allow(dead_code)]
 pub fn format_duration(seconds: f64) -> String {
     let hours = (seconds / 3600.0) as u32;
@@ -59,6 +67,100 @@ pub fn format_file_size(bytes: u64) -> String {
     format!("{:.1} {}", size, UNITS[unit_index])
 }
 
+// 取出 ffmpeg 日志行中 key 后面的数值
+pub fn value_after(line: &str, key: &str) -> Option<f64> {
+    line.split(key)
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|v| v.parse().ok())
+}
+
+// 从视频简介之类的自由文本中提取时间戳和标题，如 "00:00 Intro / 03:12 Setup"
+// 支持 M:SS、H:MM:SS 和 HH:MM:SS.mmm，标题取时间戳之后到下一个时间戳或行尾的文字
+pub fn parse_timestamp_list(text: &str) -> VideoResult<Vec<(f64, String)>> {
+    let is_clock_char = |c: char| c.is_ascii_digit() || c == ':' || c == '.';
+    
+    // 找出所有时间戳的位置
+    let mut stamps = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !c.is_ascii_digit() {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_clock_char(c) {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let token = text[start..end].trim_end_matches(['.', ':']);
+        if let Some(seconds) = parse_clock(token) {
+            stamps.push((start, start + token.len(), seconds));
+        }
+    }
+    
+    let separators: &[char] = &['-', '–', '—', '|', '/', ':', ',', ';', '(', ')', '[', ']', '•', '·'];
+    let clean = |s: &str| s.trim_matches(|c: char| c.is_whitespace() || separators.contains(&c)).to_string();
+    
+    let mut entries: Vec<(f64, String)> = Vec::new();
+    for (i, &(start, end, seconds)) in stamps.iter().enumerate() {
+        let next = stamps.get(i + 1).map(|s| s.0).unwrap_or(text.len());
+        let after = text[end..next].lines().next().unwrap_or_default();
+        let mut title = clean(after);
+        
+        // "Intro - 00:00" 这种标题在时间戳前面的写法
+        if title.is_empty() {
+            let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let previous_end = if i > 0 { stamps[i - 1].1 } else { 0 };
+            title = clean(&text[line_start.max(previous_end)..start]);
+        }
+        
+        entries.push((seconds, title));
+    }
+    
+    if entries.is_empty() {
+        return Err(VideoError::InvalidFormat("No timestamps found".to_string()));
+    }
+    
+    entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+    entries.dedup_by(|a, b| a.0 == b.0);
+    Ok(entries)
+}
+
+// 解析 M:SS、H:MM:SS 或 HH:MM:SS.mmm 格式的时间
+fn parse_clock(token: &str) -> Option<f64> {
+    let parts: Vec<&str> = token.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+    
+    let (whole, fraction) = match parts[parts.len() - 1].split_once('.') {
+        Some((whole, fraction)) if (1..=3).contains(&fraction.len()) => (whole, Some(fraction)),
+        Some(_) => return None,
+        None => (parts[parts.len() - 1], None),
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if whole.len() != 2 || !all_digits(whole) || !parts[..parts.len() - 1].iter().all(|p| all_digits(p)) {
+        return None;
+    }
+    
+    let seconds: f64 = whole.parse().ok()?;
+    let minutes: f64 = parts[parts.len() - 2].parse().ok()?;
+    if seconds >= 60.0 || (parts.len() == 3 && (minutes >= 60.0 || parts[1].len() != 2)) {
+        return None;
+    }
+    let hours: f64 = if parts.len() == 3 { parts[0].parse().ok()? } else { 0.0 };
+    let fraction = match fraction {
+        Some(f) if all_digits(f) => format!("0.{}", f).parse::<f64>().ok()?,
+        Some(_) => return None,
+        None => 0.0,
+    };
+    
+    Some(hours * 3600.0 + minutes * 60.0 + seconds + fraction)
+}
+
 pub fn validate_video_file(path: &std::path::Path) -> VideoResult<()> {
     if !path.exists() {
         return Err(VideoError::FileNotFound(path.to_string_lossy().to_string()));
@@ -78,4 +180,71 @@ pub fn validate_video_file(path: &std::path::Path) -> VideoResult<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timestamp_list_title_after_timestamp() {
+        let entries = parse_timestamp_list("00:00 Intro\n03:12 - Setup\n1:02:03 Finale").unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (0.0, "Intro".to_string()),
+                (192.0, "Setup".to_string()),
+                (3723.0, "Finale".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_list_title_before_timestamp() {
+        let entries = parse_timestamp_list("Intro - 00:00\nSetup (3:12)").unwrap();
+        assert_eq!(
+            entries,
+            vec![(0.0, "Intro".to_string()), (192.0, "Setup".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_list_accepts_milliseconds() {
+        let entries = parse_timestamp_list("01:02:03.500 Part two").unwrap();
+        assert_eq!(entries, vec![(3723.5, "Part two".to_string())]);
+    }
+
+    #[test]
+    fn parse_timestamp_list_rejects_ratios() {
+        let entries = parse_timestamp_list("Shot in 16:9\n00:10 Opening").unwrap();
+        assert_eq!(entries, vec![(10.0, "Opening".to_string())]);
+        assert!(parse_timestamp_list("Aspect 16:9 and 4:3").is_err());
+    }
+
+    #[test]
+    fn parse_timestamp_list_numbered_lines() {
+        let entries = parse_timestamp_list("1. 00:00 Intro\n2. 05:30 Main topic").unwrap();
+        assert_eq!(
+            entries,
+            vec![(0.0, "Intro".to_string()), (330.0, "Main topic".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_timestamp_list_drops_duplicates() {
+        let entries = parse_timestamp_list("01:00 First\n00:00 Start\n01:00 Again").unwrap();
+        assert_eq!(
+            entries,
+            vec![(0.0, "Start".to_string()), (60.0, "First".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_clock_formats() {
+        assert_eq!(parse_clock("3:07"), Some(187.0));
+        assert_eq!(parse_clock("1:00:00"), Some(3600.0));
+        assert_eq!(parse_clock("00:01.25"), Some(1.25));
+        assert_eq!(parse_clock("1:75"), None);
+        assert_eq!(parse_clock("1:2:03"), None);
+        assert_eq!(parse_clock("12"), None);
+    }
+}